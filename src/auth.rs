@@ -0,0 +1,353 @@
+//! DHCP authentication (option 90).
+//!
+//! Implements the delayed authentication protocol from RFC3118 and the
+//! reconfigure key protocol used to authenticate DHCPFORCERENEW messages,
+//! both using HMAC-MD5. Messages are signed and verified in their encoded
+//! form, exactly as sent or received, with the MAC in the authentication
+//! option set to zero.
+
+use hmac::{Hmac, Mac};
+use md5::Md5;
+
+/// Algorithm value for HMAC-MD5, the only algorithm defined for the protocols here.
+pub const ALGORITHM_HMAC_MD5: u8 = 1;
+
+/// Replay detection method value for a monotonically increasing counter.
+pub const RDM_MONOTONIC: u8 = 0;
+
+/// Length of an HMAC-MD5 MAC.
+pub const MAC_LENGTH: usize = 16;
+
+/// Enum representing the protocol field of option 90.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthenticationProtocol {
+    ConfigurationToken,
+    DelayedAuthentication,
+    ReconfigureKey,
+    Unknown(u8),
+}
+
+impl From<u8> for AuthenticationProtocol {
+    fn from(item: u8) -> Self {
+        match item {
+            0 => AuthenticationProtocol::ConfigurationToken,
+            1 => AuthenticationProtocol::DelayedAuthentication,
+            3 => AuthenticationProtocol::ReconfigureKey,
+            n => AuthenticationProtocol::Unknown(n),
+        }
+    }
+}
+
+impl From<AuthenticationProtocol> for u8 {
+    fn from(item: AuthenticationProtocol) -> Self {
+        match item {
+            AuthenticationProtocol::ConfigurationToken => 0,
+            AuthenticationProtocol::DelayedAuthentication => 1,
+            AuthenticationProtocol::ReconfigureKey => 3,
+            AuthenticationProtocol::Unknown(n) => n,
+        }
+    }
+}
+
+/// Type of the authentication information for the reconfigure key protocol.
+const RECONFIGURE_KEY_VALUE: u8 = 1;
+const RECONFIGURE_HMAC_MD5: u8 = 2;
+
+/// The parameter for DHCP Option 90 "Authentication".
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Authentication {
+    pub protocol: AuthenticationProtocol,
+    pub algorithm: u8,
+    pub rdm: u8,
+    pub replay_detection: u64,
+    pub information: Vec<u8>,
+}
+
+impl Authentication {
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() < 11 {
+            return None;
+        }
+
+        Some(Self {
+            protocol: bytes[0].into(),
+            algorithm: bytes[1],
+            rdm: bytes[2],
+            replay_detection: u64::from_be_bytes(bytes[3..11].try_into().ok()?),
+            information: bytes[11..].to_vec(),
+        })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes: Vec<u8> = vec![self.protocol.into(), self.algorithm, self.rdm];
+        bytes.extend_from_slice(&self.replay_detection.to_be_bytes());
+        bytes.extend_from_slice(&self.information);
+        bytes
+    }
+
+    /// Delayed authentication using the key identified by `secret_id`.
+    ///
+    /// The MAC is left zeroed, it is filled in by `sign`.
+    pub fn delayed(secret_id: u32, replay_detection: u64) -> Self {
+        let mut information = secret_id.to_be_bytes().to_vec();
+        information.extend_from_slice(&[0; MAC_LENGTH]);
+
+        Self {
+            protocol: AuthenticationProtocol::DelayedAuthentication,
+            algorithm: ALGORITHM_HMAC_MD5,
+            rdm: RDM_MONOTONIC,
+            replay_detection,
+            information,
+        }
+    }
+
+    /// Deliver a reconfigure key to a client, sent by the server in a DHCPACK.
+    pub fn reconfigure_key(key: [u8; 16], replay_detection: u64) -> Self {
+        let mut information = vec![RECONFIGURE_KEY_VALUE];
+        information.extend_from_slice(&key);
+
+        Self {
+            protocol: AuthenticationProtocol::ReconfigureKey,
+            algorithm: ALGORITHM_HMAC_MD5,
+            rdm: RDM_MONOTONIC,
+            replay_detection,
+            information,
+        }
+    }
+
+    /// Authenticate a message with a previously delivered reconfigure key.
+    ///
+    /// The MAC is left zeroed, it is filled in by `sign`.
+    pub fn reconfigure_hmac(replay_detection: u64) -> Self {
+        let mut information = vec![RECONFIGURE_HMAC_MD5];
+        information.extend_from_slice(&[0; MAC_LENGTH]);
+
+        Self {
+            protocol: AuthenticationProtocol::ReconfigureKey,
+            algorithm: ALGORITHM_HMAC_MD5,
+            rdm: RDM_MONOTONIC,
+            replay_detection,
+            information,
+        }
+    }
+
+    /// The secret ID of delayed authentication, used to look up the key to verify with.
+    pub fn secret_id(&self) -> Option<u32> {
+        match (self.protocol, self.information.get(..4)) {
+            (AuthenticationProtocol::DelayedAuthentication, Some(id)) => {
+                Some(u32::from_be_bytes(id.try_into().ok()?))
+            }
+            _ => None,
+        }
+    }
+
+    /// The key delivered by `Authentication::reconfigure_key`.
+    pub fn key(&self) -> Option<[u8; 16]> {
+        match (self.protocol, self.information.split_first()) {
+            (AuthenticationProtocol::ReconfigureKey, Some((&RECONFIGURE_KEY_VALUE, key))) => {
+                key.try_into().ok()
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether the replay detection value is newer than `last`, the last value accepted.
+    ///
+    /// Only the monotonically increasing counter method is supported, other
+    /// methods are never considered newer.
+    pub fn is_newer_than(&self, last: u64) -> bool {
+        self.rdm == RDM_MONOTONIC && self.replay_detection > last
+    }
+
+    // Offset of the MAC within the authentication information, if the option carries one.
+    fn mac_offset(&self) -> Option<usize> {
+        if self.algorithm != ALGORITHM_HMAC_MD5 {
+            return None;
+        }
+
+        let offset = match (self.protocol, self.information.first()) {
+            (AuthenticationProtocol::DelayedAuthentication, _) => 4,
+            (AuthenticationProtocol::ReconfigureKey, Some(&RECONFIGURE_HMAC_MD5)) => 1,
+            _ => return None,
+        };
+
+        (self.information.len() == offset + MAC_LENGTH).then_some(offset)
+    }
+}
+
+// Copy the encoded `message` with the MAC of its authentication option zeroed,
+// returning the copy and the position of the MAC within it.
+fn prepare_for_mac(message: &[u8]) -> Option<(Vec<u8>, usize)> {
+    let mut bytes = message.to_vec();
+
+    // The vendor area starts after the fixed fields and the magic cookie.
+    let mut index = 240;
+    if bytes.get(236..240)? != [0x63, 0x82, 0x53, 0x63] {
+        return None;
+    }
+
+    let mac = loop {
+        let code = *bytes.get(index)?;
+        match code {
+            0 => {
+                index += 1;
+                continue;
+            }
+            255 => return None,
+            _ => (),
+        }
+
+        let length = *bytes.get(index + 1)? as usize;
+        let data = bytes.get(index + 2..index + 2 + length)?;
+        if code == 90 {
+            let authentication = Authentication::from_bytes(data)?;
+            // Delayed authentication is computed with the fields relay agents change zeroed.
+            if authentication.protocol == AuthenticationProtocol::DelayedAuthentication {
+                bytes[3] = 0;
+                bytes[24..28].fill(0);
+            }
+            break index + 2 + 11 + authentication.mac_offset()?;
+        }
+        index += 2 + length;
+    };
+
+    bytes[mac..mac + MAC_LENGTH].fill(0);
+    Some((bytes, mac))
+}
+
+fn hmac_md5(key: &[u8], bytes: &[u8]) -> Hmac<Md5> {
+    let mut hmac = <Hmac<Md5> as Mac>::new_from_slice(key).expect("HMAC accepts any key length");
+    hmac.update(bytes);
+    hmac
+}
+
+/// Fill in the MAC of the authentication option in the encoded `message` using `key`.
+///
+/// The option should be created with `Authentication::delayed` or
+/// `Authentication::reconfigure_hmac`, `message` must be exactly the bytes
+/// that are sent and not be changed afterwards. Returns `None` if there is no
+/// authentication option with a MAC in the message.
+pub fn sign(message: &mut [u8], key: &[u8]) -> Option<()> {
+    let (bytes, mac) = prepare_for_mac(message)?;
+    let digest = hmac_md5(key, &bytes).finalize().into_bytes();
+
+    // Keep the original hops and relay agent address, only the MAC changes.
+    message[mac..mac + MAC_LENGTH].copy_from_slice(&digest);
+    Some(())
+}
+
+/// Verify the MAC of the authentication option in `message`, the bytes as received, using `key`.
+///
+/// Replay detection is not checked, see `Authentication::is_newer_than`.
+pub fn verify(message: &[u8], key: &[u8]) -> bool {
+    match prepare_for_mac(message) {
+        Some((bytes, mac)) => hmac_md5(key, &bytes)
+            .verify_slice(&message[mac..mac + MAC_LENGTH])
+            .is_ok(),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: &[u8] = b"0123456789abcdef";
+
+    // A 300 byte DHCPFORCERENEW carrying `authentication`, as sent by a
+    // server which does not pad the vendor area.
+    fn message(authentication: &Authentication) -> Vec<u8> {
+        let mut bytes: Vec<u8> = vec![0; 240];
+        bytes[..3].copy_from_slice(&[2, 1, 6]);
+        bytes[4..8].copy_from_slice(&[0x12, 0x34, 0x56, 0x78]);
+        bytes[16..20].copy_from_slice(&[192, 0, 2, 10]);
+        bytes[28..34].copy_from_slice(&[2, 0, 0, 0, 0, 1]);
+        bytes[236..240].copy_from_slice(&[0x63, 0x82, 0x53, 0x63]);
+
+        let information = authentication.to_bytes();
+        bytes.extend_from_slice(&[53, 1, 9, 90, information.len() as u8]);
+        bytes.extend_from_slice(&information);
+        bytes.push(255);
+        bytes.resize(300, 0);
+        bytes
+    }
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    #[test]
+    fn hmac_md5_vector() {
+        // RFC2202 test case 2.
+        let digest = hmac_md5(b"Jefe", b"what do ya want for nothing?")
+            .finalize()
+            .into_bytes();
+        assert_eq!(hex(&digest), "750c783e6ab0b503eaa86e310a5db738");
+    }
+
+    #[test]
+    fn authentication_round_trip() {
+        let key = Authentication::reconfigure_key([7; 16], 1);
+        assert_eq!(
+            Authentication::from_bytes(&key.to_bytes()),
+            Some(key.clone())
+        );
+        assert_eq!(key.key(), Some([7; 16]));
+        assert_eq!(key.secret_id(), None);
+
+        let delayed = Authentication::delayed(0xDEADBEEF, 5);
+        assert_eq!(delayed.secret_id(), Some(0xDEADBEEF));
+        assert!(delayed.is_newer_than(4));
+        assert!(!delayed.is_newer_than(5));
+
+        assert_eq!(Authentication::from_bytes(&[3, 1, 0]), None);
+    }
+
+    #[test]
+    fn sign_reconfigure() {
+        let mut bytes = message(&Authentication::reconfigure_hmac(2));
+        sign(&mut bytes, KEY).unwrap();
+
+        // The MAC follows the protocol, algorithm, RDM, replay detection and type.
+        let mac = 240 + 3 + 2 + 11 + 1;
+        assert_eq!(
+            hex(&bytes[mac..mac + MAC_LENGTH]),
+            "02d42469d212665298e1905c9665aa5b"
+        );
+        assert_eq!(bytes.len(), 300);
+
+        assert!(verify(&bytes, KEY));
+        assert!(!verify(&bytes, b"fedcba9876543210"));
+
+        // Any change to the message, including padding, invalidates the MAC.
+        let mut changed = bytes.clone();
+        changed[299] = 1;
+        assert!(!verify(&changed, KEY));
+        let mut padded = bytes.clone();
+        padded.resize(546, 0);
+        assert!(!verify(&padded, KEY));
+    }
+
+    #[test]
+    fn sign_delayed() {
+        let mut bytes = message(&Authentication::delayed(1, 1));
+        sign(&mut bytes, KEY).unwrap();
+        assert!(verify(&bytes, KEY));
+
+        // Relay agents may change hops and giaddr after signing.
+        bytes[3] = 1;
+        bytes[24..28].copy_from_slice(&[192, 0, 2, 1]);
+        assert!(verify(&bytes, KEY));
+
+        bytes[12] = 1;
+        assert!(!verify(&bytes, KEY));
+    }
+
+    #[test]
+    fn no_mac() {
+        let mut bytes = message(&Authentication::reconfigure_key([7; 16], 1));
+        assert_eq!(sign(&mut bytes, KEY), None);
+        assert!(!verify(&bytes, KEY));
+        assert!(!verify(&bytes[..200], KEY));
+    }
+}
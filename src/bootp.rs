@@ -1,3 +1,5 @@
+use crate::dhcp::DHCPOption;
+use crate::ipxe::BootClient;
 use eui48::MacAddress;
 
 // Raw BOOTP Packet.
@@ -27,8 +29,26 @@ impl RawBOOTPPacket {
     pub fn as_bytes(&self) -> &[u8] {
         unsafe { std::slice::from_raw_parts(self as *const Self as *const u8, 546) }
     }
+
+    /// Decode a raw BOOTP packet from bytes received from a socket.
+    ///
+    /// Packets shorter than 546 bytes have the vendor area zero padded.
+    /// Returns `None` if the packet is too short to hold the fixed fields or
+    /// the OpCode is invalid.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() < 236 || OpCode::try_from(bytes[0]).is_err() {
+            return None;
+        }
+
+        let mut buffer: [u8; 546] = [0; 546];
+        let length = bytes.len().min(546);
+        buffer[..length].copy_from_slice(&bytes[..length]);
+
+        Some(unsafe { std::ptr::read_unaligned(buffer.as_ptr() as *const Self) })
+    }
 }
 
+#[derive(Clone, Copy)]
 pub enum OpCode {
     BOOTREQUEST,
     BOOTREPLY,
@@ -56,6 +76,7 @@ impl TryFrom<OpCode> for u8 {
 }
 
 // Higher level BOOTP Packet representation
+#[derive(Clone)]
 pub struct BOOTPPacket {
     pub op: OpCode,
     pub hops: u8,
@@ -81,9 +102,9 @@ impl From<BOOTPPacket> for RawBOOTPPacket {
             htype: 1,
             hlen: 6,
             hops: item.hops,
-            xid: item.xid,
-            secs: item.secs,
-            flags: item.flags,
+            xid: u32::to_be(item.xid),
+            secs: u16::to_be(item.secs),
+            flags: u16::to_be(item.flags),
             ciaddr: if item.ciaddr.is_some() {
                 u32::to_be(item.ciaddr.unwrap().into())
             } else {
@@ -117,9 +138,9 @@ impl From<RawBOOTPPacket> for BOOTPPacket {
         Self {
             op: item.op.try_into().unwrap(),
             hops: item.hops,
-            xid: item.xid,
-            secs: item.secs,
-            flags: item.flags,
+            xid: u32::from_be(item.xid),
+            secs: u16::from_be(item.secs),
+            flags: u16::from_be(item.flags),
             ciaddr: if item.ciaddr == 0 {
                 None
             } else {
@@ -189,4 +210,102 @@ impl BOOTPPacket {
     pub fn get_vend(&self) -> &[u8] {
         return &self.vend;
     }
+
+    /// Create a BOOTREPLY in response to this packet.
+    ///
+    /// The transaction ID, flags, relay agent address and client hardware
+    /// address are copied from this packet, all other fields are left empty.
+    pub fn reply(&self) -> Self {
+        Self {
+            op: OpCode::BOOTREPLY,
+            hops: 0,
+            xid: self.xid,
+            secs: 0,
+            flags: self.flags,
+            ciaddr: None,
+            yiaddr: None,
+            siaddr: None,
+            giaddr: self.giaddr,
+            chaddr: self.chaddr,
+            sname: [0; 64],
+            file: [0; 128],
+            vend: [0; 312],
+        }
+    }
+
+    /// Decode the DHCP options in the vendor area.
+    ///
+    /// Returns no options if the vendor area does not start with the DHCP magic cookie.
+    pub fn get_options(&self) -> Vec<DHCPOption> {
+        if self.vend[..4] != [0x63, 0x82, 0x53, 0x63] {
+            return Vec::new();
+        }
+
+        DHCPOption::from_bytes(&self.vend[4..])
+    }
+
+    /// Encode DHCP options in to the vendor area.
+    ///
+    /// Fails with the length of the encoded options if they do not fit in to
+    /// the vendor area.
+    pub fn set_options(&mut self, options: Vec<DHCPOption>) -> Result<(), usize> {
+        let bytes = DHCPOption::to_bytes(options);
+        if bytes.len() > self.vend.len() {
+            return Err(bytes.len());
+        }
+
+        self.vend = [0; 312];
+        self.vend[..bytes.len()].copy_from_slice(&bytes);
+        Ok(())
+    }
+
+    /// Classify the network boot client this packet originates from.
+    pub fn boot_client(&self) -> Option<BootClient> {
+        crate::ipxe::classify(&self.get_options())
+    }
+
+    /// Set the boot file name, truncated to fit and null terminated.
+    pub fn set_file(&mut self, file: &str) {
+        let length = file.len().min(self.file.len() - 1);
+        self.file = [0; 128];
+        self.file[..length].copy_from_slice(&file.as_bytes()[..length]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fields_are_big_endian() {
+        let packet = BOOTPPacket::new(
+            OpCode::BOOTREQUEST,
+            0,
+            0x12345678,
+            0x0102,
+            0x8000,
+            Some(std::net::Ipv4Addr::new(192, 0, 2, 1)),
+            None,
+            None,
+            None,
+            MacAddress::new([2, 0, 0, 0, 0, 1]),
+            [0; 64],
+            [0; 128],
+            [0; 312],
+        );
+
+        let raw: RawBOOTPPacket = packet.clone().into();
+        let bytes = raw.as_bytes();
+        assert_eq!(
+            bytes[4..16],
+            [0x12, 0x34, 0x56, 0x78, 0x01, 0x02, 0x80, 0x00, 192, 0, 2, 1]
+        );
+
+        let decoded: BOOTPPacket = RawBOOTPPacket::from_bytes(&bytes[..300]).unwrap().into();
+        assert_eq!(decoded.secs, 0x0102);
+        assert_eq!(decoded.flags, 0x8000);
+        assert_eq!(decoded.xid, packet.xid);
+        assert_eq!(decoded.ciaddr, packet.ciaddr);
+        assert_eq!(decoded.reply().flags, 0x8000);
+    }
 }
@@ -0,0 +1,554 @@
+//! DHCP client state machine.
+//!
+//! `DHCPClient` performs no IO, it builds the messages a client should send
+//! and is fed the replies received along with the time they were received.
+//! Retransmission and the sockets used are left to the caller.
+
+use crate::auth::Authentication;
+use crate::bootp::{BOOTPPacket, OpCode, RawBOOTPPacket};
+use crate::dhcp::{AutoConfigure, DHCPMessageType, DHCPOption};
+use eui48::MacAddress;
+use std::net::Ipv4Addr;
+use std::time::{Duration, Instant};
+
+/// Minimum time DHCPv4 is stopped for on an IPv6-only network, regardless of
+/// the V6ONLY_WAIT value the server sends (RFC8925 MIN_V6ONLY_WAIT).
+pub const MIN_V6ONLY_WAIT: u32 = 300;
+
+/// The state of a `DHCPClient`, as in RFC2131 section 4.4.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClientState {
+    Init,
+    Selecting,
+    Requesting,
+    Bound,
+    Renewing,
+    /// DHCPv4 has been stopped as the network prefers IPv6-only clients.
+    IPv6Only,
+}
+
+/// A lease acquired by a `DHCPClient`.
+#[derive(Debug)]
+pub struct Lease {
+    pub address: Ipv4Addr,
+    pub server_identifier: Ipv4Addr,
+    pub lease_time: u32,
+    pub acquired: Instant,
+    pub options: Vec<DHCPOption>,
+}
+
+/// What the caller of `DHCPClient::receive` should do next.
+pub enum ClientAction {
+    /// Send this packet to the server.
+    Send(Box<BOOTPPacket>),
+    /// A lease has been acquired, see `DHCPClient::lease`.
+    Bound,
+    /// The server declined the request, start again with `DHCPClient::discover`.
+    Restart,
+    /// The network is IPv6-only, do not use DHCPv4 for this long.
+    IPv6Only(Duration),
+}
+
+pub struct DHCPClient {
+    chaddr: MacAddress,
+    xid: u32,
+    state: ClientState,
+    parameter_request: Vec<u8>,
+    offer: Option<(Ipv4Addr, Ipv4Addr)>,
+    lease: Option<Lease>,
+    ipv6_only_until: Option<Instant>,
+    rapid_commit: bool,
+    auto_configure: bool,
+    auto_configure_denied: bool,
+    forcerenew_nonce_capable: bool,
+    forcerenew_nonce: Option<[u8; 16]>,
+    replay_detection: u64,
+}
+
+impl DHCPClient {
+    /// Create a new client for the interface with hardware address `chaddr`.
+    ///
+    /// `xid` is the transaction ID used for messages, it should be random.
+    pub fn new(chaddr: MacAddress, xid: u32) -> Self {
+        Self {
+            chaddr,
+            xid,
+            state: ClientState::Init,
+            // Subnet mask, router, DNS servers and domain name.
+            parameter_request: vec![1, 3, 6, 15],
+            offer: None,
+            lease: None,
+            ipv6_only_until: None,
+            rapid_commit: false,
+            auto_configure: false,
+            auto_configure_denied: false,
+            forcerenew_nonce_capable: false,
+            forcerenew_nonce: None,
+            replay_detection: 0,
+        }
+    }
+
+    /// Request option 108, indicating the client can operate on an IPv6-only network.
+    ///
+    /// Servers on networks which prefer IPv6-only clients will then tell the
+    /// client to stop using DHCPv4, see `ClientAction::IPv6Only`.
+    pub fn set_ipv6_only_preferred(&mut self, enabled: bool) {
+        self.parameter_request.retain(|c| *c != 108);
+        if enabled {
+            self.parameter_request.push(108);
+        }
+    }
+
+    /// Ask for the two message exchange with option 80.
+    ///
+    /// Servers supporting it answer the DHCPDISCOVER with a DHCPACK, without
+    /// the DHCPOFFER and DHCPREQUEST.
+    pub fn set_rapid_commit(&mut self, enabled: bool) {
+        self.rapid_commit = enabled;
+    }
+
+    /// Advertise that the client will configure an IPv4 link-local address with option 116.
+    ///
+    /// This allows servers to forbid it, see `DHCPClient::may_autoconfigure`.
+    pub fn set_auto_configure(&mut self, enabled: bool) {
+        self.auto_configure = enabled;
+    }
+
+    /// Whether the client may configure an IPv4 link-local address if no lease is acquired.
+    ///
+    /// This is false if autoconfiguration has not been enabled with
+    /// `DHCPClient::set_auto_configure` or a server replied to the last
+    /// DHCPDISCOVER with option 116 set to `AutoConfigure::DoNotAutoConfigure`.
+    pub fn may_autoconfigure(&self) -> bool {
+        self.auto_configure && !self.auto_configure_denied
+    }
+
+    /// Advertise support for FORCERENEW nonce authentication with option 145.
+    ///
+    /// Servers supporting it deliver a nonce in the DHCPACK which is then
+    /// used to authenticate DHCPFORCERENEW messages. Unauthenticated
+    /// DHCPFORCERENEW messages are always ignored.
+    pub fn set_forcerenew_nonce_capable(&mut self, enabled: bool) {
+        self.forcerenew_nonce_capable = enabled;
+    }
+
+    fn requested(&self, code: u8) -> bool {
+        self.parameter_request.contains(&code)
+    }
+
+    pub fn state(&self) -> ClientState {
+        self.state
+    }
+
+    pub fn lease(&self) -> Option<&Lease> {
+        self.lease.as_ref()
+    }
+
+    fn request(&self, mut options: Vec<DHCPOption>) -> BOOTPPacket {
+        if self.forcerenew_nonce_capable {
+            options.push(DHCPOption::ForcerenewNonceCapable(vec![
+                crate::auth::ALGORITHM_HMAC_MD5,
+            ]));
+        }
+        options.push(DHCPOption::End);
+
+        let mut packet = BOOTPPacket::new(
+            OpCode::BOOTREQUEST,
+            0,
+            self.xid,
+            0,
+            // Ask for broadcast replies as the client has no address yet.
+            0x8000,
+            None,
+            None,
+            None,
+            None,
+            self.chaddr,
+            [0; 64],
+            [0; 128],
+            [0; 312],
+        );
+        // A handful of fixed size options always fit.
+        packet.set_options(options).unwrap();
+        packet
+    }
+
+    /// Start acquiring a lease by building a DHCPDISCOVER.
+    ///
+    /// Returns `None` while DHCPv4 is stopped on an IPv6-only network.
+    pub fn discover(&mut self, now: Instant) -> Option<BOOTPPacket> {
+        if let Some(until) = self.ipv6_only_until {
+            if now < until {
+                return None;
+            }
+            self.ipv6_only_until = None;
+        }
+
+        self.state = ClientState::Selecting;
+        self.offer = None;
+        self.lease = None;
+        self.auto_configure_denied = false;
+        self.forcerenew_nonce = None;
+        self.replay_detection = 0;
+
+        let mut options = vec![
+            DHCPOption::DHCPMessageType(DHCPMessageType::DHCPDiscover),
+            DHCPOption::ParameterRequest(self.parameter_request.clone()),
+        ];
+        if self.rapid_commit {
+            options.push(DHCPOption::RapidCommit);
+        }
+        if self.auto_configure {
+            options.push(DHCPOption::AutoConfigure(AutoConfigure::AutoConfigure));
+        }
+
+        Some(self.request(options))
+    }
+
+    /// Renew the current lease by building a DHCPREQUEST to be unicast to the server.
+    ///
+    /// Returns `None` if there is no lease.
+    pub fn renew(&mut self) -> Option<BOOTPPacket> {
+        let address = self.lease.as_ref()?.address;
+        self.state = ClientState::Renewing;
+
+        let mut packet = self.request(vec![
+            DHCPOption::DHCPMessageType(DHCPMessageType::DHCPRequest),
+            DHCPOption::ParameterRequest(self.parameter_request.clone()),
+        ]);
+        packet.ciaddr = Some(address);
+        packet.flags = 0;
+        Some(packet)
+    }
+
+    // RFC8925 section 3.2, only honoured if the client requested option 108.
+    fn ipv6_only_wait(&self, options: &[DHCPOption]) -> Option<u32> {
+        if !self.requested(108) {
+            return None;
+        }
+
+        options.iter().find_map(|o| match o {
+            DHCPOption::IPv6OnlyPreferred(w) => Some((*w).max(MIN_V6ONLY_WAIT)),
+            _ => None,
+        })
+    }
+
+    /// Handle a reply received at `now`, given as the bytes received from the socket.
+    ///
+    /// Returns `None` if the reply is malformed, not for this client or not
+    /// expected in the current state.
+    pub fn receive(&mut self, message: &[u8], now: Instant) -> Option<ClientAction> {
+        let reply: BOOTPPacket = RawBOOTPPacket::from_bytes(message)?.into();
+        if !matches!(reply.op, OpCode::BOOTREPLY) || reply.chaddr != self.chaddr {
+            return None;
+        }
+
+        let options = reply.get_options();
+        let mut message_type: Option<&DHCPMessageType> = None;
+        let mut server_identifier: Option<Ipv4Addr> = None;
+        let mut lease_time: Option<u32> = None;
+        let mut authentication: Option<&Authentication> = None;
+        let mut rapid_commit = false;
+        let mut auto_configure: Option<AutoConfigure> = None;
+        for option in &options {
+            match option {
+                DHCPOption::DHCPMessageType(t) => message_type = Some(t),
+                DHCPOption::ServerIdentifier(addr) => server_identifier = Some(*addr),
+                DHCPOption::IPAddressLeaseTime(t) => lease_time = Some(*t),
+                DHCPOption::Authentication(a) => authentication = Some(a),
+                DHCPOption::RapidCommit => rapid_commit = true,
+                DHCPOption::AutoConfigure(a) => auto_configure = Some(*a),
+                _ => (),
+            }
+        }
+
+        // DHCPFORCERENEW is not sent in response to the client so has its own transaction ID.
+        if reply.xid != self.xid && message_type != Some(&DHCPMessageType::DHCPForceRenew) {
+            return None;
+        }
+
+        // RFC4039, a DHCPACK carrying rapid commit answers the DHCPDISCOVER directly.
+        if self.state == ClientState::Selecting
+            && message_type == Some(&DHCPMessageType::DHCPACK)
+            && self.rapid_commit
+            && rapid_commit
+        {
+            self.offer = Some((reply.yiaddr?, server_identifier?));
+            self.state = ClientState::Requesting;
+        }
+
+        match (self.state, message_type?) {
+            (ClientState::Selecting, DHCPMessageType::DHCPOffer)
+            | (ClientState::Requesting, DHCPMessageType::DHCPACK) => {
+                if let Some(wait) = self.ipv6_only_wait(&options) {
+                    let wait = Duration::from_secs(wait as u64);
+                    self.state = ClientState::IPv6Only;
+                    self.offer = None;
+                    self.ipv6_only_until = Some(now + wait);
+                    return Some(ClientAction::IPv6Only(wait));
+                }
+            }
+            _ => (),
+        }
+
+        match (self.state, message_type?) {
+            (ClientState::Selecting, DHCPMessageType::DHCPOffer) => {
+                // RFC2563 section 2.3, an offer without an address only forbids autoconfiguration.
+                if auto_configure == Some(AutoConfigure::DoNotAutoConfigure) {
+                    self.auto_configure_denied = true;
+                }
+
+                let address = reply.yiaddr?;
+                let server_identifier = server_identifier?;
+                self.offer = Some((address, server_identifier));
+                self.state = ClientState::Requesting;
+
+                Some(ClientAction::Send(Box::new(self.request(vec![
+                    DHCPOption::DHCPMessageType(DHCPMessageType::DHCPRequest),
+                    DHCPOption::RequestIPAddress(address),
+                    DHCPOption::ServerIdentifier(server_identifier),
+                    DHCPOption::ParameterRequest(self.parameter_request.clone()),
+                ]))))
+            }
+
+            (ClientState::Requesting | ClientState::Renewing, DHCPMessageType::DHCPACK) => {
+                let (address, lease_server) = match (self.state, &self.lease) {
+                    (ClientState::Renewing, Some(lease)) => {
+                        (lease.address, lease.server_identifier)
+                    }
+                    _ => self.offer?,
+                };
+                if server_identifier.is_some_and(|s| s != lease_server) {
+                    return None;
+                }
+
+                // RFC6704 section 3.3, keep the nonce to authenticate DHCPFORCERENEW with.
+                let nonce = authentication
+                    .filter(|_| self.forcerenew_nonce_capable)
+                    .and_then(|a| Some((a.key()?, a.replay_detection)));
+                if let Some((key, replay_detection)) = nonce {
+                    self.forcerenew_nonce = Some(key);
+                    self.replay_detection = replay_detection;
+                }
+
+                self.lease = Some(Lease {
+                    address: reply.yiaddr.unwrap_or(address),
+                    server_identifier: lease_server,
+                    lease_time: lease_time?,
+                    acquired: now,
+                    options,
+                });
+                self.state = ClientState::Bound;
+                Some(ClientAction::Bound)
+            }
+
+            (ClientState::Requesting | ClientState::Renewing, DHCPMessageType::DHCPNAK) => {
+                self.state = ClientState::Init;
+                self.offer = None;
+                self.lease = None;
+                Some(ClientAction::Restart)
+            }
+
+            // RFC3203, only accepted from the lease's server with a valid
+            // MAC and a replay detection counter newer than any seen before.
+            (ClientState::Bound, DHCPMessageType::DHCPForceRenew) => {
+                let nonce = self.forcerenew_nonce?;
+                let authentication = authentication?;
+                if server_identifier != Some(self.lease.as_ref()?.server_identifier)
+                    || !authentication.is_newer_than(self.replay_detection)
+                    || !crate::auth::verify(message, &nonce)
+                {
+                    return None;
+                }
+
+                self.replay_detection = authentication.replay_detection;
+                Some(ClientAction::Send(Box::new(self.renew()?)))
+            }
+
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHADDR: [u8; 6] = [2, 0, 0, 0, 0, 1];
+    const SERVER: Ipv4Addr = Ipv4Addr::new(192, 0, 2, 1);
+    const ADDRESS: Ipv4Addr = Ipv4Addr::new(192, 0, 2, 10);
+    const NONCE: [u8; 16] = [9; 16];
+
+    // Encode without the padding dhcprs adds, as other servers send it.
+    fn encode(packet: BOOTPPacket) -> Vec<u8> {
+        let raw: RawBOOTPPacket = packet.into();
+        raw.as_bytes()[..300].to_vec()
+    }
+
+    fn reply(
+        request: &BOOTPPacket,
+        message_type: DHCPMessageType,
+        extra: Vec<DHCPOption>,
+    ) -> Vec<u8> {
+        let mut reply = request.reply();
+        reply.yiaddr = Some(ADDRESS);
+
+        let mut options = vec![
+            DHCPOption::DHCPMessageType(message_type),
+            DHCPOption::ServerIdentifier(SERVER),
+            DHCPOption::IPAddressLeaseTime(3600),
+        ];
+        options.extend(extra);
+        options.push(DHCPOption::End);
+        reply.set_options(options).unwrap();
+
+        encode(reply)
+    }
+
+    fn send(action: Option<ClientAction>) -> BOOTPPacket {
+        match action {
+            Some(ClientAction::Send(packet)) => *packet,
+            _ => panic!("expected a packet to send"),
+        }
+    }
+
+    fn bound_client() -> DHCPClient {
+        let mut client = DHCPClient::new(MacAddress::new(CHADDR), 1);
+        client.set_forcerenew_nonce_capable(true);
+        let now = Instant::now();
+
+        let discover = client.discover(now).unwrap();
+        let offer = reply(&discover, DHCPMessageType::DHCPOffer, Vec::new());
+        let request = send(client.receive(&offer, now));
+        let ack = reply(
+            &request,
+            DHCPMessageType::DHCPACK,
+            vec![crate::forcerenew::nonce_option(NONCE, 1)],
+        );
+        assert!(matches!(
+            client.receive(&ack, now),
+            Some(ClientAction::Bound)
+        ));
+
+        client
+    }
+
+    // A signed DHCPFORCERENEW as sent by a server which does not pad messages.
+    fn forcerenew(replay_detection: u64, nonce: &[u8; 16]) -> Vec<u8> {
+        let mut message = crate::forcerenew::forcerenew(
+            MacAddress::new(CHADDR),
+            ADDRESS,
+            SERVER,
+            nonce,
+            replay_detection,
+        )
+        .unwrap();
+        message.truncate(300);
+        crate::auth::sign(&mut message, nonce).unwrap();
+        message
+    }
+
+    #[test]
+    fn acquire_lease() {
+        let mut client = DHCPClient::new(MacAddress::new(CHADDR), 1);
+        let now = Instant::now();
+
+        let discover = client.discover(now).unwrap();
+        assert_eq!(discover.flags, 0x8000);
+        assert_eq!(encode(discover.clone())[10..12], [0x80, 0x00]);
+
+        let offer = reply(&discover, DHCPMessageType::DHCPOffer, Vec::new());
+        let request = send(client.receive(&offer, now));
+        assert!(request
+            .get_options()
+            .contains(&DHCPOption::RequestIPAddress(ADDRESS)));
+        assert_eq!(client.state(), ClientState::Requesting);
+
+        let ack = reply(&request, DHCPMessageType::DHCPACK, Vec::new());
+        assert!(matches!(
+            client.receive(&ack, now),
+            Some(ClientAction::Bound)
+        ));
+        assert_eq!(client.lease().unwrap().address, ADDRESS);
+
+        // Renewals are unicast from the leased address.
+        let renew = client.renew().unwrap();
+        assert_eq!(renew.flags, 0);
+        assert_eq!(renew.ciaddr, Some(ADDRESS));
+    }
+
+    #[test]
+    fn rapid_commit() {
+        let mut client = DHCPClient::new(MacAddress::new(CHADDR), 1);
+        client.set_rapid_commit(true);
+        let now = Instant::now();
+
+        let discover = client.discover(now).unwrap();
+        assert!(discover.get_options().contains(&DHCPOption::RapidCommit));
+
+        let ack = reply(
+            &discover,
+            DHCPMessageType::DHCPACK,
+            vec![DHCPOption::RapidCommit],
+        );
+        assert!(matches!(
+            client.receive(&ack, now),
+            Some(ClientAction::Bound)
+        ));
+    }
+
+    #[test]
+    fn ipv6_only() {
+        let mut client = DHCPClient::new(MacAddress::new(CHADDR), 1);
+        client.set_ipv6_only_preferred(true);
+        let now = Instant::now();
+
+        let discover = client.discover(now).unwrap();
+        let offer = reply(
+            &discover,
+            DHCPMessageType::DHCPOffer,
+            vec![DHCPOption::IPv6OnlyPreferred(10)],
+        );
+
+        // The wait is at least MIN_V6ONLY_WAIT.
+        let wait = Duration::from_secs(MIN_V6ONLY_WAIT as u64);
+        assert!(matches!(
+            client.receive(&offer, now),
+            Some(ClientAction::IPv6Only(w)) if w == wait
+        ));
+        assert!(client.discover(now).is_none());
+        assert!(client.discover(now + wait).is_some());
+    }
+
+    #[test]
+    fn forcerenew_authentication() {
+        let mut client = bound_client();
+
+        // Wrong key and replayed counters are ignored.
+        assert!(client
+            .receive(&forcerenew(2, &[0; 16]), Instant::now())
+            .is_none());
+        assert!(client
+            .receive(&forcerenew(1, &NONCE), Instant::now())
+            .is_none());
+
+        let message = forcerenew(2, &NONCE);
+        let renew = send(client.receive(&message, Instant::now()));
+        assert_eq!(renew.ciaddr, Some(ADDRESS));
+        assert_eq!(client.state(), ClientState::Renewing);
+    }
+
+    #[test]
+    fn forcerenew_without_nonce() {
+        let mut client = DHCPClient::new(MacAddress::new(CHADDR), 1);
+        let now = Instant::now();
+        let discover = client.discover(now).unwrap();
+        let request = send(client.receive(
+            &reply(&discover, DHCPMessageType::DHCPOffer, Vec::new()),
+            now,
+        ));
+        client.receive(&reply(&request, DHCPMessageType::DHCPACK, Vec::new()), now);
+
+        assert!(client.receive(&forcerenew(2, &NONCE), now).is_none());
+    }
+}
@@ -1,7 +1,12 @@
-use std::net::Ipv4Addr;
+use crate::auth::Authentication;
+use crate::dnr::DNRInstance;
+use crate::ipxe::IPXEOption;
+use std::net::{Ipv4Addr, Ipv6Addr};
 
 /// Enum representing the parameter for DHCP Option 53 "Message Type".
-#[derive(Debug)]
+///
+/// Values not registered by IANA are preserved as `Unknown`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum DHCPMessageType {
     // RFC1533
     DHCPDiscover,
@@ -14,21 +19,50 @@ pub enum DHCPMessageType {
 
     // RFC2132
     DHCPInform,
+
+    // RFC3203
+    DHCPForceRenew,
+
+    // RFC4388
+    DHCPLeaseQuery,
+    DHCPLeaseUnassigned,
+    DHCPLeaseUnknown,
+    DHCPLeaseActive,
+
+    // RFC6926
+    DHCPBulkLeaseQuery,
+    DHCPLeaseQueryDone,
+
+    // RFC7724
+    DHCPActiveLeaseQuery,
+    DHCPLeaseQueryStatus,
+    DHCPTLS,
+
+    Unknown(u8),
 }
 
-impl TryFrom<u8> for DHCPMessageType {
-    type Error = ();
-    fn try_from(item: u8) -> Result<Self, Self::Error> {
+impl From<u8> for DHCPMessageType {
+    fn from(item: u8) -> Self {
         match item {
-            1 => Ok(DHCPMessageType::DHCPDiscover),
-            2 => Ok(DHCPMessageType::DHCPOffer),
-            3 => Ok(DHCPMessageType::DHCPRequest),
-            4 => Ok(DHCPMessageType::DHCPDecline),
-            5 => Ok(DHCPMessageType::DHCPACK),
-            6 => Ok(DHCPMessageType::DHCPNAK),
-            7 => Ok(DHCPMessageType::DHCPRelease),
-            8 => Ok(DHCPMessageType::DHCPInform),
-            _ => Err(()),
+            1 => DHCPMessageType::DHCPDiscover,
+            2 => DHCPMessageType::DHCPOffer,
+            3 => DHCPMessageType::DHCPRequest,
+            4 => DHCPMessageType::DHCPDecline,
+            5 => DHCPMessageType::DHCPACK,
+            6 => DHCPMessageType::DHCPNAK,
+            7 => DHCPMessageType::DHCPRelease,
+            8 => DHCPMessageType::DHCPInform,
+            9 => DHCPMessageType::DHCPForceRenew,
+            10 => DHCPMessageType::DHCPLeaseQuery,
+            11 => DHCPMessageType::DHCPLeaseUnassigned,
+            12 => DHCPMessageType::DHCPLeaseUnknown,
+            13 => DHCPMessageType::DHCPLeaseActive,
+            14 => DHCPMessageType::DHCPBulkLeaseQuery,
+            15 => DHCPMessageType::DHCPLeaseQueryDone,
+            16 => DHCPMessageType::DHCPActiveLeaseQuery,
+            17 => DHCPMessageType::DHCPLeaseQueryStatus,
+            18 => DHCPMessageType::DHCPTLS,
+            n => DHCPMessageType::Unknown(n),
         }
     }
 }
@@ -44,12 +78,122 @@ impl From<DHCPMessageType> for u8 {
             DHCPMessageType::DHCPNAK => 6,
             DHCPMessageType::DHCPRelease => 7,
             DHCPMessageType::DHCPInform => 8,
+            DHCPMessageType::DHCPForceRenew => 9,
+            DHCPMessageType::DHCPLeaseQuery => 10,
+            DHCPMessageType::DHCPLeaseUnassigned => 11,
+            DHCPMessageType::DHCPLeaseUnknown => 12,
+            DHCPMessageType::DHCPLeaseActive => 13,
+            DHCPMessageType::DHCPBulkLeaseQuery => 14,
+            DHCPMessageType::DHCPLeaseQueryDone => 15,
+            DHCPMessageType::DHCPActiveLeaseQuery => 16,
+            DHCPMessageType::DHCPLeaseQueryStatus => 17,
+            DHCPMessageType::DHCPTLS => 18,
+            DHCPMessageType::Unknown(n) => n,
+        }
+    }
+}
+
+/// Enum representing the status code of DHCP Option 151 "Status Code".
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatusCode {
+    // RFC6926
+    Success,
+    UnspecFail,
+    QueryTerminated,
+    MalformedQuery,
+    NotAllowed,
+
+    // RFC7724
+    DataMissing,
+    ConnectionActive,
+    CatchUpComplete,
+    TLSConnectionRefused,
+
+    Unknown(u8),
+}
+
+impl From<u8> for StatusCode {
+    fn from(item: u8) -> Self {
+        match item {
+            0 => StatusCode::Success,
+            1 => StatusCode::UnspecFail,
+            2 => StatusCode::QueryTerminated,
+            3 => StatusCode::MalformedQuery,
+            4 => StatusCode::NotAllowed,
+            5 => StatusCode::DataMissing,
+            6 => StatusCode::ConnectionActive,
+            7 => StatusCode::CatchUpComplete,
+            8 => StatusCode::TLSConnectionRefused,
+            n => StatusCode::Unknown(n),
+        }
+    }
+}
+
+impl From<StatusCode> for u8 {
+    fn from(item: StatusCode) -> Self {
+        match item {
+            StatusCode::Success => 0,
+            StatusCode::UnspecFail => 1,
+            StatusCode::QueryTerminated => 2,
+            StatusCode::MalformedQuery => 3,
+            StatusCode::NotAllowed => 4,
+            StatusCode::DataMissing => 5,
+            StatusCode::ConnectionActive => 6,
+            StatusCode::CatchUpComplete => 7,
+            StatusCode::TLSConnectionRefused => 8,
+            StatusCode::Unknown(n) => n,
+        }
+    }
+}
+
+/// Enum representing the parameter for DHCP Option 156 "DHCP State".
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DHCPState {
+    Available,
+    Active,
+    Expired,
+    Released,
+    Abandoned,
+    Reset,
+    Remote,
+    Transitioning,
+    Unknown(u8),
+}
+
+impl From<u8> for DHCPState {
+    fn from(item: u8) -> Self {
+        match item {
+            1 => DHCPState::Available,
+            2 => DHCPState::Active,
+            3 => DHCPState::Expired,
+            4 => DHCPState::Released,
+            5 => DHCPState::Abandoned,
+            6 => DHCPState::Reset,
+            7 => DHCPState::Remote,
+            8 => DHCPState::Transitioning,
+            n => DHCPState::Unknown(n),
+        }
+    }
+}
+
+impl From<DHCPState> for u8 {
+    fn from(item: DHCPState) -> Self {
+        match item {
+            DHCPState::Available => 1,
+            DHCPState::Active => 2,
+            DHCPState::Expired => 3,
+            DHCPState::Released => 4,
+            DHCPState::Abandoned => 5,
+            DHCPState::Reset => 6,
+            DHCPState::Remote => 7,
+            DHCPState::Transitioning => 8,
+            DHCPState::Unknown(n) => n,
         }
     }
 }
 
 /// Enum representing the parameter for DHCP Option 46 "NetBIOS over TCP/IP Node Type".
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum NetBIOSoverTCPIPNodeType {
     Bnode,
     Pnode,
@@ -81,8 +225,205 @@ impl From<NetBIOSoverTCPIPNodeType> for u8 {
     }
 }
 
+/// Enum representing the parameter for DHCP Option 116 "Auto-Configure".
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AutoConfigure {
+    DoNotAutoConfigure,
+    AutoConfigure,
+}
+
+impl TryFrom<u8> for AutoConfigure {
+    type Error = ();
+    fn try_from(item: u8) -> Result<Self, Self::Error> {
+        match item {
+            0 => Ok(AutoConfigure::DoNotAutoConfigure),
+            1 => Ok(AutoConfigure::AutoConfigure),
+            _ => Err(()),
+        }
+    }
+}
+
+impl From<AutoConfigure> for u8 {
+    fn from(item: AutoConfigure) -> Self {
+        match item {
+            AutoConfigure::DoNotAutoConfigure => 0,
+            AutoConfigure::AutoConfigure => 1,
+        }
+    }
+}
+
+/// Enum representing the parameter for DHCP Option 120 "SIP Servers".
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SIPServers {
+    DomainNames(Vec<String>),
+    Addresses(Vec<Ipv4Addr>),
+}
+
+/// The parameter for DHCP Option 212 "6rd".
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SixRd {
+    /// Number of high order bits of the IPv4 address shared by all CEs, left out of the delegated prefix.
+    pub ipv4_mask_length: u8,
+    pub prefix_length: u8,
+    pub prefix: Ipv6Addr,
+    pub border_relays: Vec<Ipv4Addr>,
+}
+
+impl SixRd {
+    /// Whether the lengths are consistent and there is at least one border relay.
+    ///
+    /// The 6rd prefix followed by the unshared bits of the IPv4 address must
+    /// fit in an IPv6 address.
+    pub fn is_valid(&self) -> bool {
+        self.ipv4_mask_length <= 32
+            && self.prefix_length <= 128
+            && self.prefix_length as u32 + 32 - self.ipv4_mask_length as u32 <= 128
+            && !self.border_relays.is_empty()
+    }
+
+    /// Length of the prefix delegated to a CE.
+    pub fn delegated_prefix_length(&self) -> Option<u8> {
+        self.is_valid()
+            .then(|| self.prefix_length + 32 - self.ipv4_mask_length)
+    }
+}
+
+/// The parameter for DHCP Option 159 "Port Parameters".
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PortParameters {
+    /// Number of high order bits of a port excluded from the port set ("a" bits).
+    pub offset: u8,
+    pub psid_length: u8,
+    /// The PSID in the high order `psid_length` bits, the remaining bits are zero.
+    pub psid: u16,
+}
+
+impl PortParameters {
+    /// Whether the offset and PSID fit in a port and the PSID padding is zero.
+    pub fn is_valid(&self) -> bool {
+        self.offset as u32 + self.psid_length as u32 <= 16
+            && self.psid.checked_shl(self.psid_length as u32).unwrap_or(0) == 0
+    }
+
+    /// The PSID value, right aligned.
+    pub fn psid_value(&self) -> Option<u16> {
+        match self.psid_length {
+            _ if !self.is_valid() => None,
+            0 => Some(0),
+            n => Some(self.psid >> (16 - n)),
+        }
+    }
+}
+
+/// Enum representing the parameter for DHCP Option 93 "Client System Architecture Type".
+///
+/// Values are those registered by IANA in the "Processor Architecture Types" registry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClientArchitecture {
+    X86BIOS,
+    NECPC98,
+    Itanium,
+    DECAlpha,
+    ArcX86,
+    IntelLeanClient,
+    EFIIA32,
+    EFIX86_64,
+    EFIXscale,
+    EFIBC,
+    EFIARM32,
+    EFIARM64,
+    EFIX86HTTP,
+    EFIX86_64HTTP,
+    EFIBCHTTP,
+    EFIARM32HTTP,
+    EFIARM64HTTP,
+    X86BIOSHTTP,
+    EFIRISCV32,
+    EFIRISCV32HTTP,
+    EFIRISCV64,
+    EFIRISCV64HTTP,
+    Unknown(u16),
+}
+
+impl ClientArchitecture {
+    /// Whether the architecture type is one of the UEFI HTTP Boot types.
+    pub fn is_http_boot(&self) -> bool {
+        matches!(
+            self,
+            ClientArchitecture::EFIX86HTTP
+                | ClientArchitecture::EFIX86_64HTTP
+                | ClientArchitecture::EFIBCHTTP
+                | ClientArchitecture::EFIARM32HTTP
+                | ClientArchitecture::EFIARM64HTTP
+                | ClientArchitecture::X86BIOSHTTP
+                | ClientArchitecture::EFIRISCV32HTTP
+                | ClientArchitecture::EFIRISCV64HTTP
+        )
+    }
+}
+
+impl From<u16> for ClientArchitecture {
+    fn from(item: u16) -> Self {
+        match item {
+            0 => ClientArchitecture::X86BIOS,
+            1 => ClientArchitecture::NECPC98,
+            2 => ClientArchitecture::Itanium,
+            3 => ClientArchitecture::DECAlpha,
+            4 => ClientArchitecture::ArcX86,
+            5 => ClientArchitecture::IntelLeanClient,
+            6 => ClientArchitecture::EFIIA32,
+            7 => ClientArchitecture::EFIX86_64,
+            8 => ClientArchitecture::EFIXscale,
+            9 => ClientArchitecture::EFIBC,
+            10 => ClientArchitecture::EFIARM32,
+            11 => ClientArchitecture::EFIARM64,
+            15 => ClientArchitecture::EFIX86HTTP,
+            16 => ClientArchitecture::EFIX86_64HTTP,
+            17 => ClientArchitecture::EFIBCHTTP,
+            18 => ClientArchitecture::EFIARM32HTTP,
+            19 => ClientArchitecture::EFIARM64HTTP,
+            20 => ClientArchitecture::X86BIOSHTTP,
+            25 => ClientArchitecture::EFIRISCV32,
+            26 => ClientArchitecture::EFIRISCV32HTTP,
+            27 => ClientArchitecture::EFIRISCV64,
+            28 => ClientArchitecture::EFIRISCV64HTTP,
+            n => ClientArchitecture::Unknown(n),
+        }
+    }
+}
+
+impl From<ClientArchitecture> for u16 {
+    fn from(item: ClientArchitecture) -> Self {
+        match item {
+            ClientArchitecture::X86BIOS => 0,
+            ClientArchitecture::NECPC98 => 1,
+            ClientArchitecture::Itanium => 2,
+            ClientArchitecture::DECAlpha => 3,
+            ClientArchitecture::ArcX86 => 4,
+            ClientArchitecture::IntelLeanClient => 5,
+            ClientArchitecture::EFIIA32 => 6,
+            ClientArchitecture::EFIX86_64 => 7,
+            ClientArchitecture::EFIXscale => 8,
+            ClientArchitecture::EFIBC => 9,
+            ClientArchitecture::EFIARM32 => 10,
+            ClientArchitecture::EFIARM64 => 11,
+            ClientArchitecture::EFIX86HTTP => 15,
+            ClientArchitecture::EFIX86_64HTTP => 16,
+            ClientArchitecture::EFIBCHTTP => 17,
+            ClientArchitecture::EFIARM32HTTP => 18,
+            ClientArchitecture::EFIARM64HTTP => 19,
+            ClientArchitecture::X86BIOSHTTP => 20,
+            ClientArchitecture::EFIRISCV32 => 25,
+            ClientArchitecture::EFIRISCV32HTTP => 26,
+            ClientArchitecture::EFIRISCV64 => 27,
+            ClientArchitecture::EFIRISCV64HTTP => 28,
+            ClientArchitecture::Unknown(n) => n,
+        }
+    }
+}
+
 /// This enum represents all the DHCP options supported by dhcprs
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum DHCPOption {
     // RFC1533
     Pad,                                                       // 0
@@ -164,6 +505,20 @@ pub enum DHCPOption {
     StreetTalkServer(Vec<Ipv4Addr>),    // 75 n a1 a2 a3 a4 a1 a2 ...
     STDAServer(Vec<Ipv4Addr>),          // 76 n a1 a2 a3 a4 a1 a2 ...
 
+    // RFC4578
+    ClientSystemArchitecture(Vec<ClientArchitecture>), // 93 n a1 a2 a1 a2 ...
+    ClientNetworkInterfaceIdentifier(u8, u8, u8), // 94 3 t M m (t = 1 -> UNDI, M.m -> UNDI version)
+    ClientMachineIdentifier([u8; 16]),            // 97 17 0 g1 g2 ... g16
+
+    // iPXE
+    IPXEEncapsulated(Vec<IPXEOption>), // 175 n c1 l1 d1 d2 ... c2 l2 ...
+
+    // RFC5071
+    PXELINUXMagic,              // 208 4 F1 00 74 7E
+    PXELINUXConfigFile(String), // 209 n c1 c2 c3 ...
+    PXELINUXPathPrefix(String), // 210 n p1 p2 p3 ...
+    PXELINUXRebootTime(u32),    // 211 4 t1 t2 t3 t4
+
     /*// RFC4833
     TimezonePOSIX(String), // 100 N IEEE 1003.1 String
     TimezoneDB(String),    // 101 N Reference to TZ Database*/
@@ -171,6 +526,71 @@ pub enum DHCPOption {
     // RFC3442
     ClasslessStaticRoute(Vec<(Ipv4Addr, u8, Ipv4Addr)>), // 121 n d1 ... dN r1 r2 r3 r4 d1 ... dN r1 r2 r3 r4
 
+    // Microsoft
+    MicrosoftClasslessStaticRoute(Vec<(Ipv4Addr, u8, Ipv4Addr)>), // 249 n d1 ... dN r1 r2 r3 r4 d1 ... dN r1 r2 r3 r4
+
+    // draft-ietf-wrec-wpad-01
+    WebProxyAutoDiscovery(String), // 252 n u1 u2 ... 0 (PAC file URL, NUL terminated as some Windows clients drop the last character otherwise)
+
+    // RFC3004
+    UserClass(Vec<Vec<u8>>), // 77 n l1 c1 c2 ... l2 c1 c2 ...
+
+    // RFC2610
+    SLPDirectoryAgent(bool, Vec<Ipv4Addr>), // 78 n m a1 a2 a3 a4 ... (mandatory, DA addresses)
+    SLPServiceScope(bool, Vec<String>), // 79 n m s1 s2 s3 ... (mandatory, comma separated UTF-8 scopes)
+
+    // RFC4039
+    RapidCommit, // 80 0
+
+    // RFC3118
+    Authentication(Authentication), // 90 n p a r d1 ... d8 i1 i2 ...
+
+    // RFC4388
+    ClientLastTransactionTime(u32), // 91 4 t1 t2 t3 t4
+    AssociatedIP(Vec<Ipv4Addr>),    // 92 n a1 a2 a3 a4 b1 b2 b3 b4 ...
+
+    // RFC6704
+    ForcerenewNonceCapable(Vec<u8>), // 145 n a1 a2 ... (supported algorithms)
+
+    // RFC6926
+    StatusCode(StatusCode, String), // 151 n c m1 m2 ...
+    BaseTime(u32),                  // 152 4 t1 t2 t3 t4
+    StartTimeOfState(u32),          // 153 4 t1 t2 t3 t4
+    QueryStartTime(u32),            // 154 4 t1 t2 t3 t4
+    QueryEndTime(u32),              // 155 4 t1 t2 t3 t4
+    DHCPState(DHCPState),           // 156 1 s
+    DataSource(bool),               // 157 1 f (bit 0 set -> remote source)
+
+    // RFC3011
+    SubnetSelection(Ipv4Addr), // 118 4 a1 a2 a3 a4
+
+    // RFC8925
+    IPv6OnlyPreferred(u32), // 108 4 w1 w2 w3 w4 (V6ONLY_WAIT seconds)
+
+    // RFC2563
+    AutoConfigure(AutoConfigure), // 116 1 0/1
+
+    // RFC3361
+    SIPServers(SIPServers), // 120 n 0 l1 c1 c2 ... 0 ... / 120 n 1 a1 a2 a3 a4 ...
+
+    // RFC5969
+    SixRd(SixRd), // 212 n m l p1 ... p16 b1 b2 b3 b4 ...
+
+    // RFC7291
+    PCPServers(Vec<Vec<Ipv4Addr>>), // 158 n l1 a1 a2 a3 a4 ... l2 a1 a2 a3 a4 ... (one list per PCP server)
+
+    // RFC7618
+    PortParameters(PortParameters), // 159 4 o l p1 p2
+
+    // RFC8520
+    MudUrl(String), // 161 n u1 u2 u3 ... (https URL of the device's MUD file)
+
+    // RFC9463
+    EncryptedDNS(Vec<DNRInstance>), // 162 n l1 l2 p1 p2 a ... (DNR instances)
+
+    // RFC8910
+    CaptivePortal(String), // 114 n u1 u2 u3 ... (https URI or urn:ietf:params:capport:unrestricted)
+
     // Catchall
     Option(u8, Vec<u8>),
 }
@@ -190,6 +610,220 @@ macro_rules! div_ceil {
     }
 }
 
+// Decode the route descriptors of options 121 and 249, failing on prefix
+// lengths over 32 and truncated descriptors.
+fn decode_classless_routes(bytes: &[u8]) -> Option<Vec<(Ipv4Addr, u8, Ipv4Addr)>> {
+    let mut routes: Vec<(Ipv4Addr, u8, Ipv4Addr)> = Vec::new();
+    let mut rest = bytes;
+
+    while let Some((&prefix_length, data)) = rest.split_first() {
+        if prefix_length > 32 {
+            return None;
+        }
+
+        let descriptor_length = div_ceil!(prefix_length, 8) as usize;
+        if data.len() < descriptor_length + 4 {
+            return None;
+        }
+
+        let mut prefix_octets: [u8; 4] = [0; 4];
+        prefix_octets[..descriptor_length].copy_from_slice(&data[..descriptor_length]);
+        let router = &data[descriptor_length..descriptor_length + 4];
+
+        routes.push((
+            Ipv4Addr::from(prefix_octets),
+            prefix_length,
+            Ipv4Addr::new(router[0], router[1], router[2], router[3]),
+        ));
+        rest = &data[descriptor_length + 4..];
+    }
+
+    Some(routes)
+}
+
+// Decode the lists of option 158, each a length followed by the addresses of one server.
+fn decode_pcp_servers(bytes: &[u8]) -> Option<Vec<Vec<Ipv4Addr>>> {
+    let mut servers: Vec<Vec<Ipv4Addr>> = Vec::new();
+    let mut rest = bytes;
+
+    while let Some((&length, data)) = rest.split_first() {
+        let length = length as usize;
+        if length == 0 || !length.is_multiple_of(4) || data.len() < length {
+            return None;
+        }
+
+        servers.push(
+            data[..length]
+                .chunks(4)
+                .map(|a| Ipv4Addr::new(a[0], a[1], a[2], a[3]))
+                .collect(),
+        );
+        rest = &data[length..];
+    }
+
+    Some(servers)
+}
+
+fn encode_classless_routes(routes: &[(Ipv4Addr, u8, Ipv4Addr)]) -> Option<Vec<u8>> {
+    let mut bytes: Vec<u8> = Vec::new();
+
+    for (prefix, prefix_length, router) in routes {
+        if *prefix_length > 32 {
+            return None;
+        }
+
+        let descriptor_length = div_ceil!(prefix_length, 8);
+        bytes.push(*prefix_length);
+        bytes.extend_from_slice(&prefix.octets()[..descriptor_length as usize]);
+        bytes.extend_from_slice(&router.octets());
+    }
+
+    (bytes.len() <= 255).then_some(bytes)
+}
+
+/// A route in the routing table computed by `routing_table`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Route {
+    pub destination: Ipv4Addr,
+    pub prefix_length: u8,
+    pub router: Ipv4Addr,
+}
+
+impl Route {
+    // Routes are normalised so no host bits are set in the destination.
+    fn new(destination: Ipv4Addr, prefix_length: u8, router: Ipv4Addr) -> Option<Self> {
+        if prefix_length > 32 {
+            return None;
+        }
+
+        let mask = u32::MAX.checked_shl(32 - prefix_length as u32).unwrap_or(0);
+        Some(Self {
+            destination: Ipv4Addr::from(u32::from(destination) & mask),
+            prefix_length,
+            router,
+        })
+    }
+}
+
+/// Compute the routing table configured by a set of options.
+///
+/// Following RFC3442, if the classless static route option (121) is present
+/// the router (3) and static route (33) options are ignored. The Microsoft
+/// classless static route option (249) is used in its place when it is
+/// absent. Otherwise the static routes are followed by a default route via
+/// the first router. Static route destinations use their classful prefix
+/// length, or are host routes if host bits are set. Routes with prefix
+/// lengths over 32 are ignored.
+pub fn routing_table(options: &[DHCPOption]) -> Vec<Route> {
+    let classless = options
+        .iter()
+        .find_map(|o| match o {
+            DHCPOption::ClasslessStaticRoute(routes) => Some(routes),
+            _ => None,
+        })
+        .or_else(|| {
+            options.iter().find_map(|o| match o {
+                DHCPOption::MicrosoftClasslessStaticRoute(routes) => Some(routes),
+                _ => None,
+            })
+        });
+
+    if let Some(routes) = classless {
+        return routes
+            .iter()
+            .filter_map(|(destination, prefix_length, router)| {
+                Route::new(*destination, *prefix_length, *router)
+            })
+            .collect();
+    }
+
+    let mut table: Vec<Route> = Vec::new();
+    for option in options {
+        match option {
+            DHCPOption::StaticRoutes(routes) => {
+                for (destination, router) in routes {
+                    let classful_length = match destination.octets()[0] {
+                        0..=127 => 8,
+                        128..=191 => 16,
+                        _ => 24,
+                    };
+                    let host_bits = u32::from(*destination) & (u32::MAX >> classful_length);
+                    let prefix_length = if host_bits != 0 { 32 } else { classful_length };
+                    table.extend(Route::new(*destination, prefix_length, *router));
+                }
+            }
+            DHCPOption::Router(routers) => {
+                if let Some(router) = routers.first() {
+                    table.extend(Route::new(Ipv4Addr::UNSPECIFIED, 0, *router));
+                }
+            }
+            _ => (),
+        }
+    }
+
+    // The default route comes after the more specific static routes.
+    table.sort_by_key(|r| r.prefix_length == 0);
+    table
+}
+
+/// URN used in option 114 to signal that there is no captive portal.
+pub const CAPTIVE_PORTAL_UNRESTRICTED: &str = "urn:ietf:params:capport:unrestricted";
+
+// Checks for an absolute URI with one of `schemes`, a non-empty authority and
+// only printable ASCII characters as required by RFC3986.
+pub(crate) fn is_absolute_uri(uri: &str, schemes: &[&str]) -> bool {
+    match uri.split_once("://") {
+        Some((scheme, rest)) if schemes.iter().any(|s| scheme.eq_ignore_ascii_case(s)) => {
+            let authority = rest.split(['/', '?', '#']).next().unwrap_or_default();
+            !authority.is_empty() && uri.bytes().all(|b| b.is_ascii_graphic())
+        }
+        _ => false,
+    }
+}
+
+fn is_https_uri(uri: &str) -> bool {
+    is_absolute_uri(uri, &["https"])
+}
+
+fn is_captive_portal_uri(uri: &str) -> bool {
+    uri == CAPTIVE_PORTAL_UNRESTRICTED || is_https_uri(uri)
+}
+
+// Option 77 should be a list of length prefixed classes, but Windows and
+// iPXE send a single class without a length. Anything which is not a valid
+// list is treated as a single class.
+fn decode_user_class(bytes: &[u8]) -> Vec<Vec<u8>> {
+    let mut classes: Vec<Vec<u8>> = Vec::new();
+    let mut rest = bytes;
+
+    while let Some((&length, data)) = rest.split_first() {
+        if length == 0 || length as usize > data.len() {
+            return vec![bytes.to_vec()];
+        }
+        let (class, remaining) = data.split_at(length as usize);
+        classes.push(class.to_vec());
+        rest = remaining;
+    }
+
+    classes
+}
+
+/// Whether the options contain a user class (option 77) equal to `class`.
+pub fn has_user_class(options: &[DHCPOption], class: &[u8]) -> bool {
+    options.iter().any(|o| match o {
+        DHCPOption::UserClass(classes) => classes.iter().any(|c| c == class),
+        _ => false,
+    })
+}
+
+/// The MUD URL (option 161) sent by a device, used to look up its Manufacturer Usage Description.
+pub fn mud_url(options: &[DHCPOption]) -> Option<&str> {
+    options.iter().find_map(|o| match o {
+        DHCPOption::MudUrl(url) => Some(url.as_str()),
+        _ => None,
+    })
+}
+
 impl DHCPOption {
     pub fn from_bytes(bytes: &[u8]) -> Vec<DHCPOption> {
         let mut options: Vec<DHCPOption> = Vec::new();
@@ -651,7 +1285,7 @@ impl DHCPOption {
                 }
 
                 33 => {
-                    let count = break_unwrap!(iterator.next()) / 4;
+                    let count = break_unwrap!(iterator.next()) / 8;
                     let mut addresses: Vec<(Ipv4Addr, Ipv4Addr)> = Vec::new();
 
                     for _ in 0..count {
@@ -919,13 +1553,9 @@ impl DHCPOption {
                 53 => {
                     break_unwrap!(iterator.next());
 
-                    let node_type: DHCPMessageType = match break_unwrap!(iterator.next()).try_into()
-                    {
-                        Ok(n) => n,
-                        _ => continue,
-                    };
+                    let message_type: DHCPMessageType = break_unwrap!(iterator.next()).into();
 
-                    options.push(DHCPOption::DHCPMessageType(node_type));
+                    options.push(DHCPOption::DHCPMessageType(message_type));
                 }
 
                 54 => {
@@ -1236,112 +1866,631 @@ impl DHCPOption {
                     options.push(DHCPOption::STDAServer(addresses));
                 }
 
-                121 => {
-                    let mut length = break_unwrap!(iterator.next());
-                    let mut routes: Vec<(Ipv4Addr, u8, Ipv4Addr)> = Vec::new();
-
-                    while length > 0 {
-                        let prefix_length = break_unwrap!(iterator.next());
-                        let descriptor_length = div_ceil!(prefix_length, 8);
-
-                        let mut prefix_octets: [u8; 4] = [0;4];
-                        for count in 0..descriptor_length {
-                            prefix_octets[count as usize] = break_unwrap!(iterator.next());
-                        }
-
-                        let router = Ipv4Addr::new(break_unwrap!(iterator.next()),break_unwrap!(iterator.next()),break_unwrap!(iterator.next()),break_unwrap!(iterator.next()));
+                77 => {
+                    let count = break_unwrap!(iterator.next());
+                    let mut bytes: Vec<u8> = Vec::new();
 
-                        routes.push((Ipv4Addr::from(prefix_octets), prefix_length, router));
-                        length -= 1 + descriptor_length + 4;
+                    for _ in 0..count {
+                        bytes.push(break_unwrap!(iterator.next()));
                     }
 
-                    options.push(DHCPOption::ClasslessStaticRoute(routes));
+                    options.push(DHCPOption::UserClass(decode_user_class(&bytes)));
                 }
 
-                // Catchall for if we cannot decode the option to a specific enum variant.
-                n => {
+                78 => {
                     let count = break_unwrap!(iterator.next());
-
                     let mut bytes: Vec<u8> = Vec::new();
 
                     for _ in 0..count {
                         bytes.push(break_unwrap!(iterator.next()));
                     }
 
-                    options.push(DHCPOption::Option(n, bytes));
+                    options.push(match bytes.split_first() {
+                        Some((&mandatory @ (0 | 1), addresses)) if addresses.len() % 4 == 0 => {
+                            DHCPOption::SLPDirectoryAgent(
+                                mandatory == 1,
+                                addresses
+                                    .chunks(4)
+                                    .map(|a| Ipv4Addr::new(a[0], a[1], a[2], a[3]))
+                                    .collect(),
+                            )
+                        }
+                        _ => DHCPOption::Option(78, bytes),
+                    });
                 }
-            }
-        }
 
-        return options;
-    }
+                79 => {
+                    let count = break_unwrap!(iterator.next());
+                    let mut bytes: Vec<u8> = Vec::new();
 
-    pub fn to_bytes(options: Vec<DHCPOption>) -> Vec<u8> {
-        let mut bytes: Vec<u8> = Vec::new();
-        bytes.push(0x63);
-        bytes.push(0x82);
-        bytes.push(0x53);
-        bytes.push(0x63);
+                    for _ in 0..count {
+                        bytes.push(break_unwrap!(iterator.next()));
+                    }
 
-        for option in options {
-            match option {
-                DHCPOption::Pad => bytes.push(0),
-                DHCPOption::End => bytes.push(255),
+                    // An empty scope list tells agents not to use scopes.
+                    let scopes = match bytes.split_first() {
+                        Some((&mandatory @ (0 | 1), scopes)) => std::str::from_utf8(scopes)
+                            .ok()
+                            .map(|s| (mandatory == 1, s.split(',').filter(|s| !s.is_empty()))),
+                        _ => None,
+                    };
 
-                DHCPOption::SubnetMask(addr) => {
-                    bytes.push(1);
-                    bytes.push(4);
-                    bytes.extend_from_slice(&(u32::from(addr)).to_be_bytes());
+                    options.push(match scopes {
+                        Some((mandatory, scopes)) => DHCPOption::SLPServiceScope(
+                            mandatory,
+                            scopes.map(String::from).collect(),
+                        ),
+                        None => DHCPOption::Option(79, bytes),
+                    });
                 }
 
-                DHCPOption::TimeOffset(x) => {
-                    bytes.push(2);
-                    bytes.push(4);
-                    bytes.extend_from_slice(&x.to_be_bytes());
-                }
+                80 => {
+                    let count = break_unwrap!(iterator.next());
 
-                DHCPOption::Router(addrs) => {
-                    bytes.push(3);
-                    let count = addrs.len() * 4;
-                    bytes.push(count as u8);
-                    for addr in addrs {
-                        bytes.extend_from_slice(&(u32::from(addr)).to_be_bytes());
+                    // The option has no data, anything sent with it is ignored.
+                    for _ in 0..count {
+                        break_unwrap!(iterator.next());
                     }
-                }
 
-                DHCPOption::TimeServer(addrs) => {
-                    bytes.push(4);
-                    let count = addrs.len() * 4;
-                    bytes.push(count as u8);
-                    for addr in addrs {
-                        bytes.extend_from_slice(&(u32::from(addr)).to_be_bytes());
-                    }
+                    options.push(DHCPOption::RapidCommit);
                 }
 
-                DHCPOption::NameServer(addrs) => {
-                    bytes.push(5);
-                    let count = addrs.len() * 4;
-                    bytes.push(count as u8);
-                    for addr in addrs {
-                        bytes.extend_from_slice(&(u32::from(addr)).to_be_bytes());
-                    }
-                }
+                90 => {
+                    let count = break_unwrap!(iterator.next());
+                    let mut bytes: Vec<u8> = Vec::new();
 
-                DHCPOption::DomainNameServer(addrs) => {
-                    bytes.push(6);
-                    let count = addrs.len() * 4;
-                    bytes.push(count as u8);
-                    for addr in addrs {
-                        bytes.extend_from_slice(&(u32::from(addr)).to_be_bytes());
+                    for _ in 0..count {
+                        bytes.push(break_unwrap!(iterator.next()));
                     }
+
+                    options.push(match Authentication::from_bytes(&bytes) {
+                        Some(a) => DHCPOption::Authentication(a),
+                        None => DHCPOption::Option(90, bytes),
+                    });
                 }
 
-                DHCPOption::LogServer(addrs) => {
-                    bytes.push(7);
-                    let count = addrs.len() * 4;
-                    bytes.push(count as u8);
-                    for addr in addrs {
-                        bytes.extend_from_slice(&(u32::from(addr)).to_be_bytes());
+                91 => {
+                    break_unwrap!(iterator.next());
+                    let mut total: u32 = 0;
+                    for _ in 0..4 {
+                        total <<= 8;
+                        total += break_unwrap!(iterator.next()) as u32;
+                    }
+
+                    options.push(DHCPOption::ClientLastTransactionTime(total));
+                }
+
+                92 => {
+                    let count = break_unwrap!(iterator.next()) / 4;
+                    let mut addresses: Vec<Ipv4Addr> = Vec::new();
+
+                    for _ in 0..count {
+                        let (a1, a2, a3, a4) = (
+                            break_unwrap!(iterator.next()),
+                            break_unwrap!(iterator.next()),
+                            break_unwrap!(iterator.next()),
+                            break_unwrap!(iterator.next()),
+                        );
+                        addresses.push(Ipv4Addr::new(a1, a2, a3, a4));
+                    }
+
+                    options.push(DHCPOption::AssociatedIP(addresses));
+                }
+
+                93 => {
+                    let count = break_unwrap!(iterator.next()) / 2;
+                    let mut architectures: Vec<ClientArchitecture> = Vec::new();
+
+                    for _ in 0..count {
+                        let (a1, a2) = (
+                            break_unwrap!(iterator.next()),
+                            break_unwrap!(iterator.next()),
+                        );
+                        architectures.push(u16::from_be_bytes([a1, a2]).into());
+                    }
+
+                    options.push(DHCPOption::ClientSystemArchitecture(architectures));
+                }
+
+                94 => {
+                    let count = break_unwrap!(iterator.next());
+                    let mut bytes: Vec<u8> = Vec::new();
+
+                    for _ in 0..count {
+                        bytes.push(break_unwrap!(iterator.next()));
+                    }
+
+                    options.push(match bytes[..] {
+                        [t, major, minor] => {
+                            DHCPOption::ClientNetworkInterfaceIdentifier(t, major, minor)
+                        }
+                        _ => DHCPOption::Option(94, bytes),
+                    });
+                }
+
+                97 => {
+                    let count = break_unwrap!(iterator.next());
+                    let mut bytes: Vec<u8> = Vec::new();
+
+                    for _ in 0..count {
+                        bytes.push(break_unwrap!(iterator.next()));
+                    }
+
+                    // Only type 0 (a 16 octet GUID) is defined, keep anything else in the catchall.
+                    options.push(match bytes.split_first() {
+                        Some((0, guid)) if guid.len() == 16 => {
+                            DHCPOption::ClientMachineIdentifier(guid.try_into().unwrap())
+                        }
+                        _ => DHCPOption::Option(97, bytes),
+                    });
+                }
+
+                108 => {
+                    break_unwrap!(iterator.next());
+                    let mut total: u32 = 0;
+                    for _ in 0..4 {
+                        total <<= 8;
+                        total += break_unwrap!(iterator.next()) as u32;
+                    }
+
+                    options.push(DHCPOption::IPv6OnlyPreferred(total));
+                }
+
+                114 => {
+                    let count = break_unwrap!(iterator.next());
+                    let mut chars: Vec<u8> = Vec::new();
+
+                    for _ in 0..count {
+                        chars.push(break_unwrap!(iterator.next()));
+                    }
+
+                    // If data is invalid just silently fail and act as if the option didn't exist.
+                    match std::str::from_utf8(&chars) {
+                        Ok(s) if is_captive_portal_uri(s) => {
+                            options.push(DHCPOption::CaptivePortal(s.to_owned()))
+                        }
+                        _ => (),
+                    }
+                }
+
+                116 => {
+                    break_unwrap!(iterator.next());
+
+                    let auto_configure: AutoConfigure =
+                        match break_unwrap!(iterator.next()).try_into() {
+                            Ok(a) => a,
+                            _ => continue,
+                        };
+
+                    options.push(DHCPOption::AutoConfigure(auto_configure));
+                }
+
+                118 => {
+                    break_unwrap!(iterator.next());
+
+                    let (a1, a2, a3, a4) = (
+                        break_unwrap!(iterator.next()),
+                        break_unwrap!(iterator.next()),
+                        break_unwrap!(iterator.next()),
+                        break_unwrap!(iterator.next()),
+                    );
+                    options.push(DHCPOption::SubnetSelection(Ipv4Addr::new(a1, a2, a3, a4)));
+                }
+
+                120 => {
+                    let count = break_unwrap!(iterator.next());
+                    let mut bytes: Vec<u8> = Vec::new();
+
+                    for _ in 0..count {
+                        bytes.push(break_unwrap!(iterator.next()));
+                    }
+
+                    let servers = match bytes.split_first() {
+                        Some((0, names)) => crate::dns::decode_names(names)
+                            .filter(|n| !n.is_empty())
+                            .map(SIPServers::DomainNames),
+                        Some((1, addresses))
+                            if !addresses.is_empty() && addresses.len() % 4 == 0 =>
+                        {
+                            Some(SIPServers::Addresses(
+                                addresses
+                                    .chunks(4)
+                                    .map(|a| Ipv4Addr::new(a[0], a[1], a[2], a[3]))
+                                    .collect(),
+                            ))
+                        }
+                        _ => None,
+                    };
+
+                    options.push(match servers {
+                        Some(s) => DHCPOption::SIPServers(s),
+                        None => DHCPOption::Option(120, bytes),
+                    });
+                }
+
+                121 => {
+                    let count = break_unwrap!(iterator.next());
+                    let mut bytes: Vec<u8> = Vec::new();
+
+                    for _ in 0..count {
+                        bytes.push(break_unwrap!(iterator.next()));
+                    }
+
+                    options.push(match decode_classless_routes(&bytes) {
+                        Some(routes) => DHCPOption::ClasslessStaticRoute(routes),
+                        None => DHCPOption::Option(121, bytes),
+                    });
+                }
+
+                145 => {
+                    let count = break_unwrap!(iterator.next());
+                    let mut bytes: Vec<u8> = Vec::new();
+
+                    for _ in 0..count {
+                        bytes.push(break_unwrap!(iterator.next()));
+                    }
+
+                    options.push(DHCPOption::ForcerenewNonceCapable(bytes));
+                }
+
+                151 => {
+                    let count = break_unwrap!(iterator.next());
+                    if count == 0 {
+                        continue;
+                    }
+                    let status: StatusCode = break_unwrap!(iterator.next()).into();
+                    let mut chars: Vec<u8> = Vec::new();
+
+                    for _ in 1..count {
+                        chars.push(break_unwrap!(iterator.next()));
+                    }
+
+                    // An invalid message is replaced rather than losing the status code.
+                    options.push(DHCPOption::StatusCode(
+                        status,
+                        String::from_utf8_lossy(&chars).into_owned(),
+                    ));
+                }
+
+                152 => {
+                    break_unwrap!(iterator.next());
+                    let mut total: u32 = 0;
+                    for _ in 0..4 {
+                        total <<= 8;
+                        total += break_unwrap!(iterator.next()) as u32;
+                    }
+
+                    options.push(DHCPOption::BaseTime(total));
+                }
+
+                153 => {
+                    break_unwrap!(iterator.next());
+                    let mut total: u32 = 0;
+                    for _ in 0..4 {
+                        total <<= 8;
+                        total += break_unwrap!(iterator.next()) as u32;
+                    }
+
+                    options.push(DHCPOption::StartTimeOfState(total));
+                }
+
+                154 => {
+                    break_unwrap!(iterator.next());
+                    let mut total: u32 = 0;
+                    for _ in 0..4 {
+                        total <<= 8;
+                        total += break_unwrap!(iterator.next()) as u32;
+                    }
+
+                    options.push(DHCPOption::QueryStartTime(total));
+                }
+
+                155 => {
+                    break_unwrap!(iterator.next());
+                    let mut total: u32 = 0;
+                    for _ in 0..4 {
+                        total <<= 8;
+                        total += break_unwrap!(iterator.next()) as u32;
+                    }
+
+                    options.push(DHCPOption::QueryEndTime(total));
+                }
+
+                156 => {
+                    break_unwrap!(iterator.next());
+
+                    let state: DHCPState = break_unwrap!(iterator.next()).into();
+
+                    options.push(DHCPOption::DHCPState(state));
+                }
+
+                157 => {
+                    break_unwrap!(iterator.next());
+
+                    let flags = break_unwrap!(iterator.next());
+
+                    options.push(DHCPOption::DataSource(flags & 1 != 0));
+                }
+
+                158 => {
+                    let count = break_unwrap!(iterator.next());
+                    let mut bytes: Vec<u8> = Vec::new();
+
+                    for _ in 0..count {
+                        bytes.push(break_unwrap!(iterator.next()));
+                    }
+
+                    options.push(match decode_pcp_servers(&bytes) {
+                        Some(s) if !s.is_empty() => DHCPOption::PCPServers(s),
+                        _ => DHCPOption::Option(158, bytes),
+                    });
+                }
+
+                159 => {
+                    let count = break_unwrap!(iterator.next());
+                    let mut bytes: Vec<u8> = Vec::new();
+
+                    for _ in 0..count {
+                        bytes.push(break_unwrap!(iterator.next()));
+                    }
+
+                    let port_parameters = match bytes[..] {
+                        [offset, psid_length, p1, p2] => Some(PortParameters {
+                            offset,
+                            psid_length,
+                            psid: u16::from_be_bytes([p1, p2]),
+                        }),
+                        _ => None,
+                    };
+
+                    options.push(match port_parameters {
+                        Some(p) if p.is_valid() => DHCPOption::PortParameters(p),
+                        _ => DHCPOption::Option(159, bytes),
+                    });
+                }
+
+                161 => {
+                    let count = break_unwrap!(iterator.next());
+                    let mut chars: Vec<u8> = Vec::new();
+
+                    for _ in 0..count {
+                        chars.push(break_unwrap!(iterator.next()));
+                    }
+
+                    // If data is invalid just silently fail and act as if the option didn't exist.
+                    match std::str::from_utf8(&chars) {
+                        Ok(s) if is_https_uri(s) => options.push(DHCPOption::MudUrl(s.to_owned())),
+                        _ => (),
+                    }
+                }
+
+                162 => {
+                    let count = break_unwrap!(iterator.next());
+                    let mut bytes: Vec<u8> = Vec::new();
+
+                    for _ in 0..count {
+                        bytes.push(break_unwrap!(iterator.next()));
+                    }
+
+                    options.push(match DNRInstance::from_bytes(&bytes) {
+                        Some(d) if !d.is_empty() => DHCPOption::EncryptedDNS(d),
+                        _ => DHCPOption::Option(162, bytes),
+                    });
+                }
+
+                175 => {
+                    let count = break_unwrap!(iterator.next());
+                    let mut bytes: Vec<u8> = Vec::new();
+
+                    for _ in 0..count {
+                        bytes.push(break_unwrap!(iterator.next()));
+                    }
+
+                    options.push(match IPXEOption::from_bytes(&bytes) {
+                        Some(o) => DHCPOption::IPXEEncapsulated(o),
+                        None => DHCPOption::Option(175, bytes),
+                    });
+                }
+
+                208 => {
+                    let count = break_unwrap!(iterator.next());
+                    let mut bytes: Vec<u8> = Vec::new();
+
+                    for _ in 0..count {
+                        bytes.push(break_unwrap!(iterator.next()));
+                    }
+
+                    // The option is only meaningful with the fixed magic value.
+                    options.push(if bytes == [0xF1, 0x00, 0x74, 0x7E] {
+                        DHCPOption::PXELINUXMagic
+                    } else {
+                        DHCPOption::Option(208, bytes)
+                    });
+                }
+
+                209 => {
+                    let count = break_unwrap!(iterator.next());
+                    let mut chars: Vec<u8> = Vec::new();
+
+                    for _ in 0..count {
+                        chars.push(break_unwrap!(iterator.next()));
+                    }
+
+                    // If data is invalid just silently fail and act as if the option didn't exist.
+                    if let Ok(s) = std::str::from_utf8(&chars) {
+                        options.push(DHCPOption::PXELINUXConfigFile(s.to_owned()));
+                    }
+                }
+
+                210 => {
+                    let count = break_unwrap!(iterator.next());
+                    let mut chars: Vec<u8> = Vec::new();
+
+                    for _ in 0..count {
+                        chars.push(break_unwrap!(iterator.next()));
+                    }
+
+                    // If data is invalid just silently fail and act as if the option didn't exist.
+                    if let Ok(s) = std::str::from_utf8(&chars) {
+                        options.push(DHCPOption::PXELINUXPathPrefix(s.to_owned()));
+                    }
+                }
+
+                211 => {
+                    break_unwrap!(iterator.next());
+                    let mut total: u32 = 0;
+                    for _ in 0..4 {
+                        total <<= 8;
+                        total += break_unwrap!(iterator.next()) as u32;
+                    }
+
+                    options.push(DHCPOption::PXELINUXRebootTime(total));
+                }
+
+                249 => {
+                    let count = break_unwrap!(iterator.next());
+                    let mut bytes: Vec<u8> = Vec::new();
+
+                    for _ in 0..count {
+                        bytes.push(break_unwrap!(iterator.next()));
+                    }
+
+                    options.push(match decode_classless_routes(&bytes) {
+                        Some(routes) => DHCPOption::MicrosoftClasslessStaticRoute(routes),
+                        None => DHCPOption::Option(249, bytes),
+                    });
+                }
+
+                252 => {
+                    let count = break_unwrap!(iterator.next());
+                    let mut chars: Vec<u8> = Vec::new();
+
+                    for _ in 0..count {
+                        chars.push(break_unwrap!(iterator.next()));
+                    }
+
+                    // Servers commonly NUL terminate the URL.
+                    while chars.last() == Some(&0) {
+                        chars.pop();
+                    }
+
+                    // If data is invalid just silently fail and act as if the option didn't exist.
+                    if let Ok(s) = std::str::from_utf8(&chars) {
+                        options.push(DHCPOption::WebProxyAutoDiscovery(s.to_owned()));
+                    }
+                }
+
+                212 => {
+                    let count = break_unwrap!(iterator.next());
+                    let mut bytes: Vec<u8> = Vec::new();
+
+                    for _ in 0..count {
+                        bytes.push(break_unwrap!(iterator.next()));
+                    }
+
+                    let six_rd = match bytes.len() {
+                        length if length >= 22 && (length - 18).is_multiple_of(4) => Some(SixRd {
+                            ipv4_mask_length: bytes[0],
+                            prefix_length: bytes[1],
+                            prefix: Ipv6Addr::from(<[u8; 16]>::try_from(&bytes[2..18]).unwrap()),
+                            border_relays: bytes[18..]
+                                .chunks(4)
+                                .map(|a| Ipv4Addr::new(a[0], a[1], a[2], a[3]))
+                                .collect(),
+                        }),
+                        _ => None,
+                    };
+
+                    options.push(match six_rd {
+                        Some(s) if s.is_valid() => DHCPOption::SixRd(s),
+                        _ => DHCPOption::Option(212, bytes),
+                    });
+                }
+
+                // Catchall for if we cannot decode the option to a specific enum variant.
+                n => {
+                    let count = break_unwrap!(iterator.next());
+
+                    let mut bytes: Vec<u8> = Vec::new();
+
+                    for _ in 0..count {
+                        bytes.push(break_unwrap!(iterator.next()));
+                    }
+
+                    options.push(DHCPOption::Option(n, bytes));
+                }
+            }
+        }
+
+        return options;
+    }
+
+    pub fn to_bytes(options: Vec<DHCPOption>) -> Vec<u8> {
+        let mut bytes: Vec<u8> = Vec::new();
+        bytes.push(0x63);
+        bytes.push(0x82);
+        bytes.push(0x53);
+        bytes.push(0x63);
+
+        for option in options {
+            match option {
+                DHCPOption::Pad => bytes.push(0),
+                DHCPOption::End => bytes.push(255),
+
+                DHCPOption::SubnetMask(addr) => {
+                    bytes.push(1);
+                    bytes.push(4);
+                    bytes.extend_from_slice(&(u32::from(addr)).to_be_bytes());
+                }
+
+                DHCPOption::TimeOffset(x) => {
+                    bytes.push(2);
+                    bytes.push(4);
+                    bytes.extend_from_slice(&x.to_be_bytes());
+                }
+
+                DHCPOption::Router(addrs) => {
+                    bytes.push(3);
+                    let count = addrs.len() * 4;
+                    bytes.push(count as u8);
+                    for addr in addrs {
+                        bytes.extend_from_slice(&(u32::from(addr)).to_be_bytes());
+                    }
+                }
+
+                DHCPOption::TimeServer(addrs) => {
+                    bytes.push(4);
+                    let count = addrs.len() * 4;
+                    bytes.push(count as u8);
+                    for addr in addrs {
+                        bytes.extend_from_slice(&(u32::from(addr)).to_be_bytes());
+                    }
+                }
+
+                DHCPOption::NameServer(addrs) => {
+                    bytes.push(5);
+                    let count = addrs.len() * 4;
+                    bytes.push(count as u8);
+                    for addr in addrs {
+                        bytes.extend_from_slice(&(u32::from(addr)).to_be_bytes());
+                    }
+                }
+
+                DHCPOption::DomainNameServer(addrs) => {
+                    bytes.push(6);
+                    let count = addrs.len() * 4;
+                    bytes.push(count as u8);
+                    for addr in addrs {
+                        bytes.extend_from_slice(&(u32::from(addr)).to_be_bytes());
+                    }
+                }
+
+                DHCPOption::LogServer(addrs) => {
+                    bytes.push(7);
+                    let count = addrs.len() * 4;
+                    bytes.push(count as u8);
+                    for addr in addrs {
+                        bytes.extend_from_slice(&(u32::from(addr)).to_be_bytes());
                     }
                 }
 
@@ -1824,6 +2973,300 @@ impl DHCPOption {
                     }
                 }
 
+                DHCPOption::ClientSystemArchitecture(architectures) => {
+                    bytes.push(93);
+                    bytes.push((architectures.len() * 2) as u8);
+                    for architecture in architectures {
+                        bytes.extend_from_slice(&u16::from(architecture).to_be_bytes());
+                    }
+                }
+
+                DHCPOption::ClientNetworkInterfaceIdentifier(t, major, minor) => {
+                    bytes.push(94);
+                    bytes.push(3);
+                    bytes.push(t);
+                    bytes.push(major);
+                    bytes.push(minor);
+                }
+
+                DHCPOption::ClientMachineIdentifier(guid) => {
+                    bytes.push(97);
+                    bytes.push(17);
+                    bytes.push(0);
+                    bytes.extend_from_slice(&guid);
+                }
+
+                DHCPOption::IPXEEncapsulated(o) => {
+                    let b = IPXEOption::to_bytes(&o);
+                    bytes.push(175);
+                    bytes.push(b.len() as u8);
+                    bytes.extend_from_slice(&b);
+                }
+
+                DHCPOption::PXELINUXMagic => {
+                    bytes.push(208);
+                    bytes.push(4);
+                    bytes.extend_from_slice(&[0xF1, 0x00, 0x74, 0x7E]);
+                }
+
+                DHCPOption::PXELINUXConfigFile(s) => {
+                    bytes.push(209);
+                    bytes.push(s.len() as u8);
+                    bytes.extend_from_slice(s.as_bytes());
+                }
+
+                DHCPOption::PXELINUXPathPrefix(s) => {
+                    bytes.push(210);
+                    bytes.push(s.len() as u8);
+                    bytes.extend_from_slice(s.as_bytes());
+                }
+
+                DHCPOption::PXELINUXRebootTime(t) => {
+                    bytes.push(211);
+                    bytes.push(4);
+                    bytes.extend_from_slice(&t.to_be_bytes());
+                }
+
+                DHCPOption::SIPServers(servers) => {
+                    let b = match servers {
+                        SIPServers::DomainNames(names) => match crate::dns::encode_names(&names) {
+                            Some(names) => [vec![0], names].concat(),
+                            None => continue,
+                        },
+                        SIPServers::Addresses(addrs) => {
+                            let mut b = vec![1];
+                            for addr in addrs {
+                                b.extend_from_slice(&(u32::from(addr)).to_be_bytes());
+                            }
+                            b
+                        }
+                    };
+                    if b.len() > 255 {
+                        continue;
+                    }
+
+                    bytes.push(120);
+                    bytes.push(b.len() as u8);
+                    bytes.extend_from_slice(&b);
+                }
+
+                DHCPOption::SixRd(s) => {
+                    if !s.is_valid() || s.border_relays.len() > 59 {
+                        continue;
+                    }
+
+                    bytes.push(212);
+                    bytes.push((18 + s.border_relays.len() * 4) as u8);
+                    bytes.push(s.ipv4_mask_length);
+                    bytes.push(s.prefix_length);
+                    bytes.extend_from_slice(&s.prefix.octets());
+                    for addr in s.border_relays {
+                        bytes.extend_from_slice(&(u32::from(addr)).to_be_bytes());
+                    }
+                }
+
+                DHCPOption::PCPServers(servers) => {
+                    if servers.iter().any(|s| s.is_empty() || s.len() > 63) {
+                        continue;
+                    }
+
+                    let mut b: Vec<u8> = Vec::new();
+                    for server in &servers {
+                        b.push((server.len() * 4) as u8);
+                        for addr in server {
+                            b.extend_from_slice(&addr.octets());
+                        }
+                    }
+
+                    if b.is_empty() || b.len() > 255 {
+                        continue;
+                    }
+
+                    bytes.push(158);
+                    bytes.push(b.len() as u8);
+                    bytes.extend_from_slice(&b);
+                }
+
+                DHCPOption::PortParameters(p) => {
+                    if !p.is_valid() {
+                        continue;
+                    }
+
+                    bytes.push(159);
+                    bytes.push(4);
+                    bytes.push(p.offset);
+                    bytes.push(p.psid_length);
+                    bytes.extend_from_slice(&p.psid.to_be_bytes());
+                }
+
+                DHCPOption::MudUrl(s) => {
+                    if !is_https_uri(&s) || s.len() > 255 {
+                        continue;
+                    }
+
+                    bytes.push(161);
+                    bytes.push(s.len() as u8);
+                    bytes.extend_from_slice(s.as_bytes());
+                }
+
+                DHCPOption::EncryptedDNS(d) => {
+                    let b = match DNRInstance::to_bytes(&d) {
+                        Some(b) if b.len() <= 255 => b,
+                        _ => continue,
+                    };
+
+                    bytes.push(162);
+                    bytes.push(b.len() as u8);
+                    bytes.extend_from_slice(&b);
+                }
+
+                DHCPOption::CaptivePortal(s) => {
+                    if !is_captive_portal_uri(&s) || s.len() > 255 {
+                        continue;
+                    }
+
+                    bytes.push(114);
+                    bytes.push(s.len() as u8);
+                    bytes.extend_from_slice(s.as_bytes());
+                }
+
+                DHCPOption::IPv6OnlyPreferred(w) => {
+                    bytes.push(108);
+                    bytes.push(4);
+                    bytes.extend_from_slice(&w.to_be_bytes());
+                }
+
+                DHCPOption::AutoConfigure(a) => {
+                    bytes.push(116);
+                    bytes.push(1);
+                    bytes.push(a.into());
+                }
+
+                DHCPOption::SubnetSelection(addr) => {
+                    bytes.push(118);
+                    bytes.push(4);
+                    bytes.extend_from_slice(&(u32::from(addr)).to_be_bytes());
+                }
+
+                DHCPOption::UserClass(classes) => {
+                    let length = classes.iter().map(|c| c.len() + 1).sum::<usize>();
+                    if length > 255 || classes.iter().any(|c| c.is_empty()) {
+                        continue;
+                    }
+
+                    bytes.push(77);
+                    bytes.push(length as u8);
+                    for class in classes {
+                        bytes.push(class.len() as u8);
+                        bytes.extend_from_slice(&class);
+                    }
+                }
+
+                DHCPOption::SLPDirectoryAgent(mandatory, addrs) => {
+                    if addrs.len() > 63 {
+                        continue;
+                    }
+
+                    bytes.push(78);
+                    bytes.push((1 + addrs.len() * 4) as u8);
+                    bytes.push(mandatory as u8);
+                    for addr in addrs {
+                        bytes.extend_from_slice(&(u32::from(addr)).to_be_bytes());
+                    }
+                }
+
+                DHCPOption::SLPServiceScope(mandatory, scopes) => {
+                    if scopes.iter().any(|s| s.is_empty() || s.contains(',')) {
+                        continue;
+                    }
+
+                    let scopes = scopes.join(",");
+                    if scopes.len() > 254 {
+                        continue;
+                    }
+
+                    bytes.push(79);
+                    bytes.push((1 + scopes.len()) as u8);
+                    bytes.push(mandatory as u8);
+                    bytes.extend_from_slice(scopes.as_bytes());
+                }
+
+                DHCPOption::RapidCommit => {
+                    bytes.push(80);
+                    bytes.push(0);
+                }
+
+                DHCPOption::Authentication(a) => {
+                    let b = a.to_bytes();
+                    bytes.push(90);
+                    bytes.push(b.len() as u8);
+                    bytes.extend_from_slice(&b);
+                }
+
+                DHCPOption::ClientLastTransactionTime(t) => {
+                    bytes.push(91);
+                    bytes.push(4);
+                    bytes.extend_from_slice(&t.to_be_bytes());
+                }
+
+                DHCPOption::AssociatedIP(addrs) => {
+                    bytes.push(92);
+                    let count = addrs.len() * 4;
+                    bytes.push(count as u8);
+                    for addr in addrs {
+                        bytes.extend_from_slice(&(u32::from(addr)).to_be_bytes());
+                    }
+                }
+
+                DHCPOption::ForcerenewNonceCapable(b) => {
+                    bytes.push(145);
+                    bytes.push(b.len() as u8);
+                    bytes.extend_from_slice(&b);
+                }
+
+                DHCPOption::StatusCode(c, m) => {
+                    bytes.push(151);
+                    bytes.push((m.len() + 1) as u8);
+                    bytes.push(c.into());
+                    bytes.extend_from_slice(m.as_bytes());
+                }
+
+                DHCPOption::BaseTime(t) => {
+                    bytes.push(152);
+                    bytes.push(4);
+                    bytes.extend_from_slice(&t.to_be_bytes());
+                }
+
+                DHCPOption::StartTimeOfState(t) => {
+                    bytes.push(153);
+                    bytes.push(4);
+                    bytes.extend_from_slice(&t.to_be_bytes());
+                }
+
+                DHCPOption::QueryStartTime(t) => {
+                    bytes.push(154);
+                    bytes.push(4);
+                    bytes.extend_from_slice(&t.to_be_bytes());
+                }
+
+                DHCPOption::QueryEndTime(t) => {
+                    bytes.push(155);
+                    bytes.push(4);
+                    bytes.extend_from_slice(&t.to_be_bytes());
+                }
+
+                DHCPOption::DHCPState(s) => {
+                    bytes.push(156);
+                    bytes.push(1);
+                    bytes.push(s.into());
+                }
+
+                DHCPOption::DataSource(remote) => {
+                    bytes.push(157);
+                    bytes.push(1);
+                    bytes.push(remote as u8);
+                }
+
                 DHCPOption::Option(n, b) => {
                     bytes.push(n);
                     bytes.push(b.len() as u8);
@@ -1831,14 +3274,37 @@ impl DHCPOption {
                 }
 
                 DHCPOption::ClasslessStaticRoute(routes) => {
+                    let b = match encode_classless_routes(&routes) {
+                        Some(b) => b,
+                        None => continue,
+                    };
+
                     bytes.push(121);
-                    bytes.push(routes.iter().fold(0, |acc, (_, prefix_length, _)| acc + 1 + div_ceil!(prefix_length, 8) + 4));
-                    for (prefix, prefix_length, router) in routes {
-                        let descriptor_length = div_ceil!(prefix_length, 8);
-                        bytes.push(prefix_length);
-                        bytes.extend_from_slice(&prefix.octets()[..descriptor_length as usize]);
-                        bytes.extend_from_slice(&router.octets());
+                    bytes.push(b.len() as u8);
+                    bytes.extend_from_slice(&b);
+                }
+
+                DHCPOption::WebProxyAutoDiscovery(s) => {
+                    let s = s.trim_end_matches('\0');
+                    if s.len() + 1 > 255 {
+                        continue;
                     }
+
+                    bytes.push(252);
+                    bytes.push((s.len() + 1) as u8);
+                    bytes.extend_from_slice(s.as_bytes());
+                    bytes.push(0);
+                }
+
+                DHCPOption::MicrosoftClasslessStaticRoute(routes) => {
+                    let b = match encode_classless_routes(&routes) {
+                        Some(b) => b,
+                        None => continue,
+                    };
+
+                    bytes.push(249);
+                    bytes.push(b.len() as u8);
+                    bytes.extend_from_slice(&b);
                 }
             }
         }
@@ -1846,3 +3312,577 @@ impl DHCPOption {
         return bytes;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dnr::SvcParam;
+
+    fn encode(option: DHCPOption) -> Vec<u8> {
+        // Strip the magic cookie and the end option.
+        let bytes = DHCPOption::to_bytes(vec![option, DHCPOption::End]);
+        bytes[4..bytes.len() - 1].to_vec()
+    }
+
+    fn decode(bytes: &[u8]) -> Vec<DHCPOption> {
+        DHCPOption::from_bytes(bytes)
+    }
+
+    fn assert_round_trip(option: DHCPOption) {
+        assert_eq!(decode(&encode(option.clone())), vec![option]);
+    }
+
+    #[test]
+    fn pxe_client_options() {
+        assert_round_trip(DHCPOption::ClientSystemArchitecture(vec![
+            ClientArchitecture::EFIX86_64,
+            ClientArchitecture::Unknown(0x1234),
+        ]));
+        assert_round_trip(DHCPOption::ClientNetworkInterfaceIdentifier(1, 3, 10));
+        assert_round_trip(DHCPOption::ClientMachineIdentifier([7; 16]));
+
+        assert_eq!(
+            encode(DHCPOption::ClientSystemArchitecture(vec![
+                ClientArchitecture::EFIX86_64
+            ])),
+            [93, 2, 0, 7]
+        );
+    }
+
+    #[test]
+    fn architecture_types() {
+        // Values as in the IANA registry, following the RFC4578 erratum.
+        assert_eq!(ClientArchitecture::from(7), ClientArchitecture::EFIX86_64);
+        assert_eq!(ClientArchitecture::from(9), ClientArchitecture::EFIBC);
+        assert_eq!(u16::from(ClientArchitecture::EFIX86_64), 7);
+        assert!(ClientArchitecture::from(16).is_http_boot());
+        assert!(!ClientArchitecture::from(7).is_http_boot());
+    }
+
+    #[test]
+    fn pxelinux_options() {
+        assert_round_trip(DHCPOption::PXELINUXMagic);
+        assert_round_trip(DHCPOption::PXELINUXConfigFile(
+            "pxelinux.cfg/default".to_owned(),
+        ));
+        assert_round_trip(DHCPOption::PXELINUXPathPrefix("/tftpboot/".to_owned()));
+        assert_round_trip(DHCPOption::PXELINUXRebootTime(300));
+
+        assert_eq!(
+            encode(DHCPOption::PXELINUXMagic),
+            [208, 4, 0xF1, 0x00, 0x74, 0x7E]
+        );
+        assert_eq!(
+            decode(&[208, 4, 0xF1, 0x00, 0x74, 0x7F]),
+            vec![DHCPOption::Option(208, vec![0xF1, 0x00, 0x74, 0x7F])]
+        );
+    }
+
+    #[test]
+    fn captive_portal() {
+        assert_round_trip(DHCPOption::CaptivePortal(
+            "https://portal.example/".to_owned(),
+        ));
+        assert_round_trip(DHCPOption::CaptivePortal(
+            CAPTIVE_PORTAL_UNRESTRICTED.to_owned(),
+        ));
+
+        // The longest URI which fits is still encoded.
+        let uri = format!("https://example.com/{}", "a".repeat(255 - 20));
+        assert_round_trip(DHCPOption::CaptivePortal(uri.clone()));
+
+        let uri = uri + "a";
+        assert_eq!(encode(DHCPOption::CaptivePortal(uri)), Vec::<u8>::new());
+        assert_eq!(
+            encode(DHCPOption::CaptivePortal(
+                "http://portal.example/".to_owned()
+            )),
+            Vec::<u8>::new()
+        );
+
+        // Invalid URIs are dropped.
+        assert_eq!(decode(b"\x72\x08http://a"), Vec::new());
+    }
+
+    #[test]
+    fn uris() {
+        assert!(is_absolute_uri("https://a", &["https"]));
+        assert!(is_absolute_uri("HTTPS://a/b?c#d", &["https"]));
+        assert!(is_absolute_uri("http://a", &["http", "https"]));
+        assert!(!is_absolute_uri("http://a", &["https"]));
+        assert!(!is_absolute_uri("https:///path", &["https"]));
+        assert!(!is_absolute_uri("https://a/b c", &["https"]));
+        assert!(!is_absolute_uri("https://ä", &["https"]));
+    }
+
+    #[test]
+    fn ipv6_only_preferred() {
+        assert_round_trip(DHCPOption::IPv6OnlyPreferred(1800));
+        assert_eq!(
+            encode(DHCPOption::IPv6OnlyPreferred(0x12345678)),
+            [108, 4, 0x12, 0x34, 0x56, 0x78]
+        );
+    }
+
+    #[test]
+    fn subnet_selection() {
+        assert_round_trip(DHCPOption::SubnetSelection(Ipv4Addr::new(192, 0, 2, 0)));
+        assert_eq!(
+            encode(DHCPOption::SubnetSelection(Ipv4Addr::new(192, 0, 2, 0))),
+            [118, 4, 192, 0, 2, 0]
+        );
+    }
+
+    #[test]
+    fn message_types() {
+        assert_eq!(DHCPMessageType::from(9), DHCPMessageType::DHCPForceRenew);
+        assert_eq!(DHCPMessageType::from(18), DHCPMessageType::DHCPTLS);
+        assert_eq!(DHCPMessageType::from(19), DHCPMessageType::Unknown(19));
+
+        for n in 1..=u8::MAX {
+            assert_eq!(u8::from(DHCPMessageType::from(n)), n);
+        }
+        assert_round_trip(DHCPOption::DHCPMessageType(
+            DHCPMessageType::DHCPLeaseQueryStatus,
+        ));
+    }
+
+    #[test]
+    fn leasequery_options() {
+        let options = [
+            DHCPOption::ClientLastTransactionTime(60),
+            DHCPOption::AssociatedIP(vec![
+                Ipv4Addr::new(192, 0, 2, 1),
+                Ipv4Addr::new(192, 0, 2, 2),
+            ]),
+            DHCPOption::StatusCode(StatusCode::NotAllowed, "denied".to_owned()),
+            DHCPOption::StatusCode(StatusCode::Unknown(200), String::new()),
+            DHCPOption::BaseTime(1_700_000_000),
+            DHCPOption::StartTimeOfState(10),
+            DHCPOption::QueryStartTime(20),
+            DHCPOption::QueryEndTime(30),
+            DHCPOption::DHCPState(DHCPState::Expired),
+            DHCPOption::DataSource(true),
+            DHCPOption::DataSource(false),
+        ];
+        for option in options {
+            assert_round_trip(option);
+        }
+
+        assert_eq!(
+            encode(DHCPOption::StatusCode(
+                StatusCode::MalformedQuery,
+                "bad".to_owned()
+            )),
+            [151, 4, 3, b'b', b'a', b'd']
+        );
+    }
+
+    #[test]
+    fn authentication() {
+        assert_round_trip(DHCPOption::Authentication(Authentication::delayed(1, 2)));
+        assert_round_trip(DHCPOption::Authentication(Authentication::reconfigure_key(
+            [7; 16], 3,
+        )));
+
+        let bytes = encode(DHCPOption::Authentication(Authentication::delayed(1, 2)));
+        assert_eq!(bytes[..13], [90, 31, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 2]);
+        assert_eq!(bytes.len(), 33);
+
+        // Too short for the fixed fields.
+        assert_eq!(
+            decode(&[90, 3, 2, 1, 0]),
+            [DHCPOption::Option(90, vec![2, 1, 0])]
+        );
+    }
+
+    #[test]
+    fn forcerenew_nonce_capable() {
+        assert_round_trip(DHCPOption::ForcerenewNonceCapable(vec![1]));
+        assert_eq!(
+            encode(DHCPOption::ForcerenewNonceCapable(vec![1, 2])),
+            [145, 2, 1, 2]
+        );
+    }
+
+    #[test]
+    fn user_class() {
+        let option = DHCPOption::UserClass(vec![b"iPXE".to_vec(), b"gPXE".to_vec()]);
+        assert_round_trip(option.clone());
+        assert_eq!(encode(option.clone()), b"\x4D\x0A\x04iPXE\x04gPXE");
+        assert!(has_user_class(std::slice::from_ref(&option), b"gPXE"));
+        assert!(!has_user_class(&[option], b"PXE"));
+
+        // A single class without a length, as sent by Windows and iPXE.
+        let option = decode(b"\x4D\x04iPXE");
+        assert_eq!(option, [DHCPOption::UserClass(vec![b"iPXE".to_vec()])]);
+        assert!(has_user_class(&option, b"iPXE"));
+
+        // Classes which do not fit in the option, and empty classes, cannot be encoded.
+        assert_eq!(
+            encode(DHCPOption::UserClass(vec![vec![b'a'; 254]])).len(),
+            257
+        );
+        assert!(encode(DHCPOption::UserClass(vec![vec![b'a'; 255]])).is_empty());
+        assert!(encode(DHCPOption::UserClass(vec![vec![b'a'; 200], vec![b'b'; 60]])).is_empty());
+        assert!(encode(DHCPOption::UserClass(vec![Vec::new()])).is_empty());
+    }
+
+    #[test]
+    fn rapid_commit() {
+        assert_round_trip(DHCPOption::RapidCommit);
+        assert_eq!(encode(DHCPOption::RapidCommit), [80, 0]);
+
+        // Option 80 has no data, anything sent with it is ignored.
+        assert_eq!(decode(&[80, 1, 0]), [DHCPOption::RapidCommit]);
+    }
+
+    #[test]
+    fn auto_configure() {
+        assert_round_trip(DHCPOption::AutoConfigure(AutoConfigure::AutoConfigure));
+        assert_round_trip(DHCPOption::AutoConfigure(AutoConfigure::DoNotAutoConfigure));
+        assert_eq!(
+            encode(DHCPOption::AutoConfigure(AutoConfigure::AutoConfigure)),
+            [116, 1, 1]
+        );
+
+        // Undefined values are dropped without losing the options after them.
+        assert_eq!(decode(&[116, 1, 2, 80, 0]), [DHCPOption::RapidCommit]);
+    }
+
+    #[test]
+    fn sip_servers() {
+        assert_round_trip(DHCPOption::SIPServers(SIPServers::Addresses(vec![
+            Ipv4Addr::new(192, 0, 2, 1),
+            Ipv4Addr::new(192, 0, 2, 2),
+        ])));
+        assert_round_trip(DHCPOption::SIPServers(SIPServers::DomainNames(vec![
+            "example.com".to_owned(),
+            "sip.example.com".to_owned(),
+        ])));
+
+        // Names are compressed against each other.
+        let option = DHCPOption::SIPServers(SIPServers::DomainNames(vec![
+            "example.com".to_owned(),
+            "foo.example.com".to_owned(),
+        ]));
+        assert_eq!(
+            encode(option),
+            b"\x78\x14\x00\x07example\x03com\x00\x03foo\xC0\x00"
+        );
+
+        // A compression pointer to itself.
+        assert_eq!(
+            decode(&[120, 3, 0, 0xC0, 0]),
+            [DHCPOption::Option(120, vec![0, 0xC0, 0])]
+        );
+    }
+
+    #[test]
+    fn classless_static_routes() {
+        let router = Ipv4Addr::new(192, 0, 2, 1);
+        let routes = vec![
+            (Ipv4Addr::new(10, 0, 0, 0), 8, router),
+            (Ipv4Addr::new(192, 0, 2, 128), 25, router),
+            (Ipv4Addr::UNSPECIFIED, 0, router),
+        ];
+        assert_round_trip(DHCPOption::ClasslessStaticRoute(routes.clone()));
+        assert_round_trip(DHCPOption::MicrosoftClasslessStaticRoute(routes));
+        assert_eq!(
+            encode(DHCPOption::ClasslessStaticRoute(vec![
+                (Ipv4Addr::new(10, 0, 0, 0), 8, router),
+                (Ipv4Addr::UNSPECIFIED, 0, router),
+            ])),
+            [121, 11, 8, 10, 192, 0, 2, 1, 0, 192, 0, 2, 1]
+        );
+
+        // Truncated routes and prefix lengths over 32 are kept raw.
+        assert_eq!(
+            decode(&[121, 2, 33, 0]),
+            [DHCPOption::Option(121, vec![33, 0])]
+        );
+        assert_eq!(
+            decode(&[249, 3, 8, 10, 192]),
+            [DHCPOption::Option(249, vec![8, 10, 192])]
+        );
+    }
+
+    #[test]
+    fn routing_tables() {
+        let a = Ipv4Addr::new(192, 0, 2, 1);
+        let b = Ipv4Addr::new(192, 0, 2, 2);
+        let route = |destination: [u8; 4], prefix_length, router| Route {
+            destination: Ipv4Addr::from(destination),
+            prefix_length,
+            router,
+        };
+
+        // Static routes use their classful prefix, then the default route via the first router.
+        let classful = vec![
+            DHCPOption::Router(vec![a, b]),
+            DHCPOption::StaticRoutes(vec![
+                (Ipv4Addr::new(10, 0, 0, 0), b),
+                (Ipv4Addr::new(172, 16, 0, 0), b),
+                (Ipv4Addr::new(192, 168, 1, 5), b),
+            ]),
+        ];
+        assert_eq!(
+            routing_table(&classful),
+            [
+                route([10, 0, 0, 0], 8, b),
+                route([172, 16, 0, 0], 16, b),
+                route([192, 168, 1, 5], 32, b),
+                route([0, 0, 0, 0], 0, a),
+            ]
+        );
+
+        // Option 249 replaces the classful routes, option 121 takes precedence over it.
+        let mut options = classful.clone();
+        options.push(DHCPOption::MicrosoftClasslessStaticRoute(vec![(
+            Ipv4Addr::new(10, 1, 0, 0),
+            16,
+            a,
+        )]));
+        assert_eq!(routing_table(&options), [route([10, 1, 0, 0], 16, a)]);
+
+        options.push(DHCPOption::ClasslessStaticRoute(vec![
+            (Ipv4Addr::new(10, 2, 3, 4), 16, b),
+            (Ipv4Addr::new(10, 3, 0, 0), 33, b),
+        ]));
+        assert_eq!(routing_table(&options), [route([10, 2, 0, 0], 16, b)]);
+    }
+
+    #[test]
+    fn web_proxy_auto_discovery() {
+        let url = "http://wpad.example/wpad.dat".to_owned();
+        assert_round_trip(DHCPOption::WebProxyAutoDiscovery(url.clone()));
+
+        // URLs are sent NUL terminated, with or without the terminator in the value.
+        assert_eq!(
+            encode(DHCPOption::WebProxyAutoDiscovery("http://w/".to_owned())),
+            b"\xFC\x0Ahttp://w/\x00"
+        );
+        assert_eq!(
+            encode(DHCPOption::WebProxyAutoDiscovery(
+                "http://w/\0\0".to_owned()
+            )),
+            b"\xFC\x0Ahttp://w/\x00"
+        );
+        assert_eq!(
+            decode(b"\xFC\x0Bhttp://w/\x00\x00"),
+            [DHCPOption::WebProxyAutoDiscovery("http://w/".to_owned())]
+        );
+        assert_eq!(
+            decode(b"\xFC\x09http://w/"),
+            [DHCPOption::WebProxyAutoDiscovery("http://w/".to_owned())]
+        );
+
+        // The terminator counts towards the length of the option.
+        let longest = format!("http://w/{}", "a".repeat(245));
+        assert_eq!(longest.len(), 254);
+        assert_round_trip(DHCPOption::WebProxyAutoDiscovery(longest.clone()));
+        assert!(encode(DHCPOption::WebProxyAutoDiscovery(longest + "a")).is_empty());
+    }
+
+    #[test]
+    fn six_rd() {
+        let six_rd = SixRd {
+            ipv4_mask_length: 8,
+            prefix_length: 32,
+            prefix: "2001:db8::".parse().unwrap(),
+            border_relays: vec![Ipv4Addr::new(192, 0, 2, 1)],
+        };
+        assert_eq!(six_rd.delegated_prefix_length(), Some(56));
+        assert_round_trip(DHCPOption::SixRd(six_rd.clone()));
+
+        let mut bytes = encode(DHCPOption::SixRd(six_rd.clone()));
+        assert_eq!(bytes[..4], [212, 22, 8, 32]);
+        assert_eq!(bytes[20..], [192, 0, 2, 1]);
+
+        // The delegated prefix would not fit in an IPv6 address.
+        let invalid = SixRd {
+            prefix_length: 120,
+            ..six_rd.clone()
+        };
+        assert_eq!(invalid.delegated_prefix_length(), None);
+        assert!(encode(DHCPOption::SixRd(invalid.clone())).is_empty());
+        bytes[3] = 120;
+        assert_eq!(
+            decode(&bytes),
+            [DHCPOption::Option(212, bytes[2..].to_vec())]
+        );
+
+        // At most 59 border relays fit in the option.
+        let mut relays = SixRd {
+            border_relays: vec![Ipv4Addr::new(192, 0, 2, 1); 59],
+            ..six_rd
+        };
+        assert_round_trip(DHCPOption::SixRd(relays.clone()));
+        relays.border_relays.push(Ipv4Addr::new(192, 0, 2, 2));
+        assert!(encode(DHCPOption::SixRd(relays)).is_empty());
+    }
+
+    #[test]
+    fn encrypted_dns() {
+        let instances = vec![
+            DNRInstance::adn_only(1, "resolver.example"),
+            DNRInstance {
+                priority: 2,
+                adn: "doh.example".to_owned(),
+                addresses: vec![Ipv4Addr::new(192, 0, 2, 1), Ipv4Addr::new(192, 0, 2, 2)],
+                svc_params: vec![
+                    SvcParam::ALPN(vec!["h2".to_owned(), "h3".to_owned()]),
+                    SvcParam::Port(443),
+                    SvcParam::DoHPath("/dns-query{?dns}".to_owned()),
+                ],
+            },
+        ];
+        assert_round_trip(DHCPOption::EncryptedDNS(instances));
+
+        assert_eq!(
+            encode(DHCPOption::EncryptedDNS(vec![DNRInstance::adn_only(
+                1, "a.b"
+            )])),
+            [162, 10, 0, 8, 0, 1, 5, 1, b'a', 1, b'b', 0]
+        );
+
+        // Service parameters are sorted by key when encoded.
+        let mut instance = DNRInstance::adn_only(1, "a.b");
+        instance.addresses = vec![Ipv4Addr::new(192, 0, 2, 1)];
+        instance.svc_params = vec![SvcParam::Port(853), SvcParam::ALPN(vec!["dot".to_owned()])];
+        let sorted = DNRInstance {
+            svc_params: vec![SvcParam::ALPN(vec!["dot".to_owned()]), SvcParam::Port(853)],
+            ..instance.clone()
+        };
+        assert_eq!(
+            decode(&encode(DHCPOption::EncryptedDNS(vec![instance]))),
+            [DHCPOption::EncryptedDNS(vec![sorted])]
+        );
+
+        // Instances in ADN-only mode cannot have service parameters.
+        let mut adn_only = DNRInstance::adn_only(1, "a.b");
+        adn_only.svc_params.push(SvcParam::Port(853));
+        assert!(encode(DHCPOption::EncryptedDNS(vec![adn_only])).is_empty());
+
+        // An ADN with a compression pointer.
+        assert_eq!(
+            decode(&[162, 6, 0, 4, 0, 1, 1, 0xC0]),
+            [DHCPOption::Option(162, vec![0, 4, 0, 1, 1, 0xC0])]
+        );
+    }
+
+    #[test]
+    fn mud_urls() {
+        let url = "https://example.com/mud/device.json";
+        assert_round_trip(DHCPOption::MudUrl(url.to_owned()));
+        assert_eq!(
+            encode(DHCPOption::MudUrl("https://a".to_owned())),
+            b"\xA1\x09https://a"
+        );
+        assert_eq!(mud_url(&[DHCPOption::MudUrl(url.to_owned())]), Some(url));
+        assert_eq!(mud_url(&[DHCPOption::RapidCommit]), None);
+
+        // Only https URLs of at most 255 characters are allowed.
+        let longest = format!("https://a/{}", "a".repeat(245));
+        assert_round_trip(DHCPOption::MudUrl(longest.clone()));
+        assert!(encode(DHCPOption::MudUrl(longest + "a")).is_empty());
+        assert!(encode(DHCPOption::MudUrl("http://a".to_owned())).is_empty());
+        assert!(encode(DHCPOption::MudUrl("https://".to_owned())).is_empty());
+        assert!(decode(b"\xA1\x08http://a").is_empty());
+    }
+
+    #[test]
+    fn pcp_servers() {
+        let a = Ipv4Addr::new(192, 0, 2, 1);
+        let b = Ipv4Addr::new(192, 0, 2, 2);
+        assert_round_trip(DHCPOption::PCPServers(vec![vec![a, b], vec![a]]));
+        assert_eq!(
+            encode(DHCPOption::PCPServers(vec![vec![a, b], vec![a]])),
+            [158, 14, 8, 192, 0, 2, 1, 192, 0, 2, 2, 4, 192, 0, 2, 1]
+        );
+
+        assert!(encode(DHCPOption::PCPServers(Vec::new())).is_empty());
+        assert!(encode(DHCPOption::PCPServers(vec![Vec::new()])).is_empty());
+        assert_eq!(
+            decode(&[158, 3, 4, 192, 0]),
+            [DHCPOption::Option(158, vec![4, 192, 0])]
+        );
+        assert_eq!(decode(&[158, 1, 0]), [DHCPOption::Option(158, vec![0])]);
+    }
+
+    #[test]
+    fn port_parameters() {
+        let parameters = PortParameters {
+            offset: 6,
+            psid_length: 8,
+            psid: 0x3400,
+        };
+        assert_eq!(parameters.psid_value(), Some(0x34));
+        assert_round_trip(DHCPOption::PortParameters(parameters));
+        assert_eq!(
+            encode(DHCPOption::PortParameters(parameters)),
+            [159, 4, 6, 8, 0x34, 0x00]
+        );
+
+        // Bits after the PSID must be zero.
+        let padded = PortParameters {
+            psid: 0x3401,
+            ..parameters
+        };
+        assert_eq!(padded.psid_value(), None);
+        assert!(encode(DHCPOption::PortParameters(padded)).is_empty());
+        assert_eq!(
+            decode(&[159, 4, 6, 8, 0x34, 0x01]),
+            [DHCPOption::Option(159, vec![6, 8, 0x34, 0x01])]
+        );
+        assert_eq!(
+            decode(&[159, 3, 6, 8, 0x34]),
+            [DHCPOption::Option(159, vec![6, 8, 0x34])]
+        );
+
+        let no_psid = PortParameters {
+            offset: 0,
+            psid_length: 0,
+            psid: 0,
+        };
+        assert_eq!(no_psid.psid_value(), Some(0));
+    }
+
+    #[test]
+    fn service_location_protocol() {
+        let a = Ipv4Addr::new(192, 0, 2, 1);
+        assert_round_trip(DHCPOption::SLPDirectoryAgent(true, vec![a]));
+        assert_round_trip(DHCPOption::SLPDirectoryAgent(false, Vec::new()));
+        assert_round_trip(DHCPOption::SLPServiceScope(
+            false,
+            vec!["a".to_owned(), "b".to_owned()],
+        ));
+        assert_eq!(
+            encode(DHCPOption::SLPServiceScope(
+                true,
+                vec!["a".to_owned(), "b".to_owned()]
+            )),
+            [79, 4, 1, b'a', b',', b'b']
+        );
+
+        // An empty list tells agents not to use scopes, empty scopes are not allowed.
+        assert_round_trip(DHCPOption::SLPServiceScope(false, Vec::new()));
+        assert!(encode(DHCPOption::SLPServiceScope(false, vec![String::new()])).is_empty());
+        assert!(encode(DHCPOption::SLPServiceScope(false, vec!["a,b".to_owned()])).is_empty());
+        assert_eq!(
+            decode(&[79, 4, 0, b'a', b',', b',']),
+            [DHCPOption::SLPServiceScope(false, vec!["a".to_owned()])]
+        );
+
+        // Truncated addresses and undefined mandatory values are kept raw.
+        assert_eq!(
+            decode(&[78, 3, 2, 1, 1]),
+            [DHCPOption::Option(78, vec![2, 1, 1])]
+        );
+        assert_eq!(
+            decode(&[78, 3, 1, 1, 1]),
+            [DHCPOption::Option(78, vec![1, 1, 1])]
+        );
+        assert_eq!(decode(&[79, 1, 2]), [DHCPOption::Option(79, vec![2])]);
+    }
+}
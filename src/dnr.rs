@@ -0,0 +1,200 @@
+//! Discovery of Network-designated Resolvers (option 162).
+//!
+//! RFC9463 advertises encrypted DNS resolvers (DNS over TLS, HTTPS or QUIC)
+//! as one or more DNR instances, each naming the resolver with an
+//! authentication domain name (ADN) and optionally its addresses and the
+//! service parameters used to connect to it. An instance with only an ADN
+//! ("ADN-only mode") leaves the addresses to be resolved by the client.
+
+use std::net::Ipv4Addr;
+
+/// A service parameter of a DNR instance, with the same meaning as in SVCB records.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SvcParam {
+    /// Supported protocols, such as "dot", "h2" or "h3".
+    ALPN(Vec<String>), // 1 n l1 c1 c2 ... l2 c1 c2 ...
+    Port(u16), // 3 2 p1 p2
+    /// URI template for DNS over HTTPS, relative to the ADN.
+    DoHPath(String), // 7 n c1 c2 ...
+
+    // Catchall
+    Other(u16, Vec<u8>),
+}
+
+impl SvcParam {
+    fn key(&self) -> u16 {
+        match self {
+            SvcParam::ALPN(_) => 1,
+            SvcParam::Port(_) => 3,
+            SvcParam::DoHPath(_) => 7,
+            SvcParam::Other(key, _) => *key,
+        }
+    }
+
+    fn decode(key: u16, value: &[u8]) -> Option<Self> {
+        let param = match (key, value) {
+            (1, _) => {
+                let mut protocols: Vec<String> = Vec::new();
+                let mut rest = value;
+                while let Some((&length, data)) = rest.split_first() {
+                    let protocol = data.get(..length as usize).filter(|p| !p.is_empty())?;
+                    protocols.push(std::str::from_utf8(protocol).ok()?.to_owned());
+                    rest = &data[length as usize..];
+                }
+                SvcParam::ALPN(protocols)
+            }
+            (3, [p1, p2]) => SvcParam::Port(u16::from_be_bytes([*p1, *p2])),
+            (3, _) => return None,
+            (7, _) => SvcParam::DoHPath(std::str::from_utf8(value).ok()?.to_owned()),
+            (key, value) => SvcParam::Other(key, value.to_vec()),
+        };
+
+        Some(param)
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        match self {
+            SvcParam::ALPN(protocols) => {
+                let mut bytes: Vec<u8> = Vec::new();
+                for protocol in protocols {
+                    bytes.push(protocol.len() as u8);
+                    bytes.extend_from_slice(protocol.as_bytes());
+                }
+                bytes
+            }
+            SvcParam::Port(port) => port.to_be_bytes().to_vec(),
+            SvcParam::DoHPath(path) => path.as_bytes().to_vec(),
+            SvcParam::Other(_, value) => value.clone(),
+        }
+    }
+}
+
+/// A DNR instance of option 162.
+///
+/// Instances without addresses are in ADN-only mode and must not have
+/// service parameters.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DNRInstance {
+    /// Lower values are preferred.
+    pub priority: u16,
+    pub adn: String,
+    pub addresses: Vec<Ipv4Addr>,
+    pub svc_params: Vec<SvcParam>,
+}
+
+impl DNRInstance {
+    /// An instance in ADN-only mode.
+    pub fn adn_only(priority: u16, adn: &str) -> Self {
+        Self {
+            priority,
+            adn: adn.to_owned(),
+            addresses: Vec::new(),
+            svc_params: Vec::new(),
+        }
+    }
+
+    pub fn is_adn_only(&self) -> bool {
+        self.addresses.is_empty()
+    }
+
+    /// Decode the DNR instances filling the data of option 162.
+    ///
+    /// Returns `None` if any instance is malformed.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Vec<DNRInstance>> {
+        let mut instances: Vec<DNRInstance> = Vec::new();
+        let mut rest = bytes;
+
+        while !rest.is_empty() {
+            let length = u16::from_be_bytes([*rest.first()?, *rest.get(1)?]) as usize;
+            let data = rest.get(2..2 + length)?;
+            instances.push(Self::decode(data)?);
+            rest = &rest[2 + length..];
+        }
+
+        Some(instances)
+    }
+
+    fn decode(data: &[u8]) -> Option<Self> {
+        let priority = u16::from_be_bytes([*data.first()?, *data.get(1)?]);
+        let adn_length = *data.get(2)? as usize;
+        let adn = crate::dns::decode_uncompressed_name(data.get(3..3 + adn_length)?)?;
+
+        let mut instance = Self::adn_only(priority, &adn);
+        let rest = &data[3 + adn_length..];
+        let (&addresses_length, rest) = match rest.split_first() {
+            Some(r) => r,
+            None => return Some(instance),
+        };
+
+        let addresses_length = addresses_length as usize;
+        if addresses_length == 0 || !addresses_length.is_multiple_of(4) {
+            return None;
+        }
+        instance.addresses = rest
+            .get(..addresses_length)?
+            .chunks(4)
+            .map(|a| Ipv4Addr::new(a[0], a[1], a[2], a[3]))
+            .collect();
+
+        // Keys must be in strictly increasing order.
+        let mut rest = &rest[addresses_length..];
+        let mut last_key: Option<u16> = None;
+        while !rest.is_empty() {
+            let key = u16::from_be_bytes([*rest.first()?, *rest.get(1)?]);
+            let length = u16::from_be_bytes([*rest.get(2)?, *rest.get(3)?]) as usize;
+            let value = rest.get(4..4 + length)?;
+            if last_key.is_some_and(|k| k >= key) {
+                return None;
+            }
+
+            instance.svc_params.push(SvcParam::decode(key, value)?);
+            last_key = Some(key);
+            rest = &rest[4 + length..];
+        }
+
+        Some(instance)
+    }
+
+    /// Encode DNR instances as the data of option 162.
+    ///
+    /// Service parameters are sorted by key. Returns `None` if an ADN is not
+    /// a valid domain name, an instance in ADN-only mode has service
+    /// parameters or a field is too long.
+    pub fn to_bytes(instances: &[DNRInstance]) -> Option<Vec<u8>> {
+        let mut bytes: Vec<u8> = Vec::new();
+
+        for instance in instances {
+            let adn = crate::dns::encode_name(&instance.adn).filter(|a| a.len() > 1)?;
+
+            let mut data: Vec<u8> = instance.priority.to_be_bytes().to_vec();
+            data.push(adn.len() as u8);
+            data.extend_from_slice(&adn);
+
+            if instance.is_adn_only() {
+                if !instance.svc_params.is_empty() {
+                    return None;
+                }
+            } else {
+                let addresses_length: u8 = (instance.addresses.len() * 4).try_into().ok()?;
+                data.push(addresses_length);
+                for address in &instance.addresses {
+                    data.extend_from_slice(&address.octets());
+                }
+
+                let mut svc_params: Vec<&SvcParam> = instance.svc_params.iter().collect();
+                svc_params.sort_by_key(|p| p.key());
+                for param in svc_params {
+                    let value = param.encode();
+                    data.extend_from_slice(&param.key().to_be_bytes());
+                    data.extend_from_slice(&u16::try_from(value.len()).ok()?.to_be_bytes());
+                    data.extend_from_slice(&value);
+                }
+            }
+
+            bytes.extend_from_slice(&u16::try_from(data.len()).ok()?.to_be_bytes());
+            bytes.extend_from_slice(&data);
+        }
+
+        Some(bytes)
+    }
+}
@@ -0,0 +1,238 @@
+// DNS wire format domain name encoding (RFC1035 section 3.1) used by options
+// carrying domain names. Compression pointers are offsets from the start of
+// the encoded names.
+
+const MAX_NAME_LENGTH: usize = 255;
+const MAX_LABEL_LENGTH: usize = 63;
+
+fn labels(name: &str) -> Option<Vec<&str>> {
+    let name = name.strip_suffix('.').unwrap_or(name);
+    if name.is_empty() {
+        return Some(Vec::new());
+    }
+
+    let labels: Vec<&str> = name.split('.').collect();
+    let valid = labels
+        .iter()
+        .all(|l| !l.is_empty() && l.len() <= MAX_LABEL_LENGTH)
+        && name.len() + 2 <= MAX_NAME_LENGTH;
+    valid.then_some(labels)
+}
+
+/// Encode `name` without compression.
+///
+/// Returns `None` if it is not a valid domain name.
+pub(crate) fn encode_name(name: &str) -> Option<Vec<u8>> {
+    let mut bytes: Vec<u8> = Vec::new();
+    for label in labels(name)? {
+        bytes.push(label.len() as u8);
+        bytes.extend_from_slice(label.as_bytes());
+    }
+    bytes.push(0);
+    Some(bytes)
+}
+
+/// Encode `names`, replacing suffixes already encoded with compression pointers.
+///
+/// Returns `None` if any name is not a valid domain name.
+pub(crate) fn encode_names(names: &[String]) -> Option<Vec<u8>> {
+    let mut bytes: Vec<u8> = Vec::new();
+    let mut suffixes: Vec<(String, usize)> = Vec::new();
+
+    for name in names {
+        let labels = labels(name)?;
+        let mut compressed = false;
+
+        for i in 0..labels.len() {
+            let suffix = labels[i..].join(".").to_ascii_lowercase();
+            if let Some((_, offset)) = suffixes.iter().find(|(s, _)| *s == suffix) {
+                bytes.extend_from_slice(&(0xC000 | *offset as u16).to_be_bytes());
+                compressed = true;
+                break;
+            }
+
+            // Pointers only have 14 bits for the offset.
+            if bytes.len() < 0x4000 {
+                suffixes.push((suffix, bytes.len()));
+            }
+            bytes.push(labels[i].len() as u8);
+            bytes.extend_from_slice(labels[i].as_bytes());
+        }
+
+        if !compressed {
+            bytes.push(0);
+        }
+    }
+
+    Some(bytes)
+}
+
+// Decode the name starting at `start`, returning it and the offset after it.
+fn decode_name(bytes: &[u8], start: usize) -> Option<(String, usize)> {
+    let mut labels: Vec<String> = Vec::new();
+    let mut length = 0;
+    let mut index = start;
+    let mut end: Option<usize> = None;
+
+    loop {
+        let label_length = *bytes.get(index)? as usize;
+        match label_length {
+            0 => {
+                let end = end.unwrap_or(index + 1);
+                return Some((labels.join("."), end));
+            }
+
+            1..=63 => {
+                let label = bytes.get(index + 1..index + 1 + label_length)?;
+                length += label_length + 1;
+                if length + 1 > MAX_NAME_LENGTH {
+                    return None;
+                }
+                labels.push(std::str::from_utf8(label).ok()?.to_owned());
+                index += 1 + label_length;
+            }
+
+            // Compression pointer, only pointing backwards so decoding always ends.
+            0xC0..=0xFF => {
+                let pointer = u16::from_be_bytes([*bytes.get(index)?, *bytes.get(index + 1)?]);
+                let target = (pointer & 0x3FFF) as usize;
+                if target >= index {
+                    return None;
+                }
+                end.get_or_insert(index + 2);
+                index = target;
+            }
+
+            _ => return None,
+        }
+    }
+}
+
+/// Decode a list of names filling `bytes`.
+///
+/// Returns `None` if any name is malformed.
+pub(crate) fn decode_names(bytes: &[u8]) -> Option<Vec<String>> {
+    let mut names: Vec<String> = Vec::new();
+    let mut index = 0;
+
+    while index < bytes.len() {
+        let (name, next) = decode_name(bytes, index)?;
+        names.push(name);
+        index = next;
+    }
+
+    Some(names)
+}
+
+/// Decode a single name without compression filling `bytes`.
+///
+/// Returns `None` if the name is malformed, compressed or does not fill `bytes`.
+pub(crate) fn decode_uncompressed_name(bytes: &[u8]) -> Option<String> {
+    let mut labels: Vec<&str> = Vec::new();
+    let mut rest = bytes;
+
+    loop {
+        let (&length, data) = rest.split_first()?;
+        match length as usize {
+            0 => return data.is_empty().then(|| labels.join(".")),
+            length if length <= MAX_LABEL_LENGTH => {
+                labels.push(std::str::from_utf8(data.get(..length)?).ok()?);
+                rest = &data[length..];
+            }
+            _ => return None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|n| n.to_string()).collect()
+    }
+
+    #[test]
+    fn encode_uncompressed() {
+        assert_eq!(
+            encode_name("example.com."),
+            Some(b"\x07example\x03com\x00".to_vec())
+        );
+        assert_eq!(encode_name(""), Some(vec![0]));
+        assert_eq!(encode_name("a..b"), None);
+        assert_eq!(encode_name(&"a".repeat(64)), None);
+        assert_eq!(encode_name(&vec!["a".repeat(63); 4].join(".")), None);
+    }
+
+    #[test]
+    fn compression() {
+        // RFC3361 section 3.1 example.
+        let list = names(&["example.com", "foo.example.com"]);
+        let bytes = encode_names(&list).unwrap();
+        assert_eq!(bytes, b"\x07example\x03com\x00\x03foo\xC0\x00");
+        assert_eq!(decode_names(&bytes), Some(list));
+
+        // Suffixes are matched case insensitively and by whole labels only.
+        let list = names(&["a.example.com", "B.EXAMPLE.COM", "xample.com"]);
+        let bytes = encode_names(&list).unwrap();
+        assert_eq!(
+            bytes,
+            b"\x01a\x07example\x03com\x00\x01B\xC0\x02\x06xample\xC0\x0A"
+        );
+        assert_eq!(
+            decode_names(&bytes),
+            Some(names(&["a.example.com", "B.example.com", "xample.com"]))
+        );
+    }
+
+    #[test]
+    fn malformed_names() {
+        // Pointers to themselves or forwards could loop forever.
+        assert_eq!(decode_names(b"\xC0\x00"), None);
+        assert_eq!(decode_names(b"\x01a\xC0\x02\x00"), None);
+        assert_eq!(decode_names(b"\x01a\x00\x01b\xC0\x03"), None);
+        // Truncated labels and pointers, reserved label types.
+        assert_eq!(decode_names(b"\x05abc"), None);
+        assert_eq!(decode_names(b"\x01a\xC0"), None);
+        assert_eq!(decode_names(b"\x40a\x00"), None);
+        assert_eq!(decode_names(b"\x01a"), None);
+
+        // Names longer than 255 octets, even when built from pointers.
+        let label = [b'a'; 63];
+        let mut bytes: Vec<u8> = Vec::new();
+        for _ in 0..4 {
+            bytes.push(63);
+            bytes.extend_from_slice(&label);
+        }
+        bytes.push(0);
+        assert_eq!(decode_names(&bytes), None);
+
+        let mut bytes: Vec<u8> = vec![63];
+        bytes.extend_from_slice(&label);
+        bytes.push(0);
+        let mut previous = 0;
+        for _ in 0..3 {
+            let start = bytes.len();
+            bytes.push(63);
+            bytes.extend_from_slice(&label);
+            bytes.extend_from_slice(&[0xC0, previous]);
+            previous = start as u8;
+        }
+        assert_eq!(
+            decode_names(&bytes[..bytes.len() - 66]).map(|n| n.len()),
+            Some(3)
+        );
+        assert_eq!(decode_names(&bytes), None);
+    }
+
+    #[test]
+    fn uncompressed_name() {
+        assert_eq!(
+            decode_uncompressed_name(b"\x03dns\x07example\x00"),
+            Some("dns.example".to_owned())
+        );
+        assert_eq!(decode_uncompressed_name(b"\x03dns\xC0\x00"), None);
+        assert_eq!(decode_uncompressed_name(b"\x03dns\x00\x00"), None);
+        assert_eq!(decode_uncompressed_name(b"\x03dns"), None);
+    }
+}
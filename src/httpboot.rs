@@ -0,0 +1,186 @@
+//! UEFI HTTP Boot server side helpers.
+//!
+//! UEFI HTTP Boot clients identify themselves with the vendor class
+//! `HTTPClient:Arch:xxxxx:UNDI:yyyzzz` and only accept offers which echo the
+//! vendor class as `HTTPClient` and carry an absolute URL as the boot file.
+
+use crate::bootp::{BOOTPPacket, OpCode};
+use crate::dhcp::DHCPOption;
+use crate::pxe::{BootVendorClass, VendorClassKind};
+use std::net::Ipv4Addr;
+
+/// Whether `url` is an absolute http or https URL, as required for the boot file.
+pub fn is_boot_url(url: &str) -> bool {
+    crate::dhcp::is_absolute_uri(url, &["http", "https"])
+}
+
+/// Build a reply to a request from a UEFI HTTP Boot client.
+///
+/// `options` should contain the options for the reply, including the message
+/// type and server identifier, the vendor class and boot file name are added
+/// to them. `yiaddr` is the address being offered, or `None` when answering
+/// as a proxy. Returns `None` if the request is not from an HTTP Boot client,
+/// `url` is not an absolute http or https URL or the options do not fit.
+pub fn reply(
+    request: &BOOTPPacket,
+    yiaddr: Option<Ipv4Addr>,
+    url: &str,
+    options: Vec<DHCPOption>,
+) -> Option<BOOTPPacket> {
+    if !matches!(request.op, OpCode::BOOTREQUEST) || !is_boot_url(url) {
+        return None;
+    }
+
+    let vendor_class = BootVendorClass::from_options(&request.get_options())?;
+    if vendor_class.kind != VendorClassKind::HTTPClient {
+        return None;
+    }
+
+    let mut reply_options: Vec<DHCPOption> = options
+        .into_iter()
+        .filter(|o| {
+            !matches!(
+                o,
+                DHCPOption::End | DHCPOption::ClassIdentifier(_) | DHCPOption::BootfileName(_)
+            )
+        })
+        .collect();
+    reply_options.push(DHCPOption::ClassIdentifier(b"HTTPClient".to_vec()));
+    reply_options.push(DHCPOption::BootfileName(url.to_owned()));
+    reply_options.push(DHCPOption::End);
+
+    let mut reply = request.reply();
+    reply.yiaddr = yiaddr;
+    // Some firmware reads the URL from the file field, use it when it fits.
+    if url.len() < reply.file.len() {
+        reply.set_file(url);
+    }
+    reply.set_options(reply_options).ok()?;
+
+    Some(reply)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use eui48::MacAddress;
+
+    fn request(class: &[u8]) -> BOOTPPacket {
+        let mut request = BOOTPPacket::new(
+            OpCode::BOOTREQUEST,
+            0,
+            1,
+            0,
+            0x8000,
+            None,
+            None,
+            None,
+            None,
+            MacAddress::new([2, 0, 0, 0, 0, 1]),
+            [0; 64],
+            [0; 128],
+            [0; 312],
+        );
+        request
+            .set_options(vec![
+                DHCPOption::ClassIdentifier(class.to_vec()),
+                DHCPOption::End,
+            ])
+            .unwrap();
+        request
+    }
+
+    const CLASS: &[u8] = b"HTTPClient:Arch:00016:UNDI:003001";
+
+    #[test]
+    fn boot_urls() {
+        assert!(is_boot_url("http://192.0.2.1/boot.efi"));
+        assert!(is_boot_url("HTTPS://boot.example/boot.efi"));
+        assert!(!is_boot_url("tftp://192.0.2.1/boot.efi"));
+        assert!(!is_boot_url("/boot.efi"));
+        assert!(!is_boot_url("http:///boot.efi"));
+        assert!(!is_boot_url("http://boot.example/my boot.efi"));
+    }
+
+    #[test]
+    fn reply_to_http_client() {
+        let url = "http://192.0.2.1/boot.efi";
+        let reply = reply(
+            &request(CLASS),
+            Some(Ipv4Addr::new(192, 0, 2, 10)),
+            url,
+            Vec::new(),
+        )
+        .unwrap();
+
+        assert_eq!(reply.yiaddr, Some(Ipv4Addr::new(192, 0, 2, 10)));
+        assert_eq!(reply.flags, 0x8000);
+        assert_eq!(
+            &reply.file[..url.len() + 1],
+            format!("{}\0", url).as_bytes()
+        );
+        assert_eq!(
+            reply.get_options(),
+            vec![
+                DHCPOption::ClassIdentifier(b"HTTPClient".to_vec()),
+                DHCPOption::BootfileName(url.to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn long_url() {
+        // Too long for the file field, the URL is only sent in option 67.
+        let url = format!("http://boot.example/{}", "a".repeat(200));
+        let reply = reply(&request(CLASS), None, &url, Vec::new()).unwrap();
+
+        assert_eq!(reply.file, [0; 128]);
+        assert!(reply
+            .get_options()
+            .contains(&DHCPOption::BootfileName(url.clone())));
+    }
+
+    #[test]
+    fn options_are_replaced() {
+        let options = vec![
+            DHCPOption::ServerIdentifier(Ipv4Addr::new(192, 0, 2, 1)),
+            DHCPOption::ClassIdentifier(b"PXEClient".to_vec()),
+            DHCPOption::BootfileName("pxelinux.0".to_owned()),
+            DHCPOption::End,
+        ];
+        let reply = reply(&request(CLASS), None, "http://a/b", options).unwrap();
+
+        assert_eq!(
+            reply.get_options(),
+            vec![
+                DHCPOption::ServerIdentifier(Ipv4Addr::new(192, 0, 2, 1)),
+                DHCPOption::ClassIdentifier(b"HTTPClient".to_vec()),
+                DHCPOption::BootfileName("http://a/b".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn ignored_requests() {
+        let url = "http://192.0.2.1/boot.efi";
+        assert!(reply(
+            &request(b"PXEClient:Arch:00007:UNDI:003016"),
+            None,
+            url,
+            Vec::new()
+        )
+        .is_none());
+        assert!(reply(
+            &request(b"HTTPClient:Arch:00016:UNDI"),
+            None,
+            url,
+            Vec::new()
+        )
+        .is_none());
+        assert!(reply(&request(CLASS), None, "boot.efi", Vec::new()).is_none());
+
+        let mut bootreply = request(CLASS);
+        bootreply.op = OpCode::BOOTREPLY;
+        assert!(reply(&bootreply, None, url, Vec::new()).is_none());
+    }
+}
@@ -0,0 +1,334 @@
+//! iPXE encapsulated options (option 175) and boot client classification.
+//!
+//! iPXE reports the features it was built with as sub-options of option 175
+//! in every request. Detecting requests from iPXE allows a server to answer
+//! them with a script instead of chainloading iPXE again, which would loop.
+
+use crate::dhcp::DHCPOption;
+
+/// Enum representing the feature indicator sub-options of option 175.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IPXEFeature {
+    PXEExt,
+    ISCSI,
+    AoE,
+    HTTP,
+    HTTPS,
+    TFTP,
+    FTP,
+    DNS,
+    BzImage,
+    Multiboot,
+    SLAM,
+    SRP,
+    NBI,
+    PXE,
+    ELF,
+    COMBOOT,
+    EFI,
+    FCoE,
+    VLAN,
+    Menu,
+    SDI,
+    NFS,
+}
+
+impl TryFrom<u8> for IPXEFeature {
+    type Error = ();
+    fn try_from(item: u8) -> Result<Self, Self::Error> {
+        match item {
+            16 => Ok(IPXEFeature::PXEExt),
+            17 => Ok(IPXEFeature::ISCSI),
+            18 => Ok(IPXEFeature::AoE),
+            19 => Ok(IPXEFeature::HTTP),
+            20 => Ok(IPXEFeature::HTTPS),
+            21 => Ok(IPXEFeature::TFTP),
+            22 => Ok(IPXEFeature::FTP),
+            23 => Ok(IPXEFeature::DNS),
+            24 => Ok(IPXEFeature::BzImage),
+            25 => Ok(IPXEFeature::Multiboot),
+            26 => Ok(IPXEFeature::SLAM),
+            27 => Ok(IPXEFeature::SRP),
+            32 => Ok(IPXEFeature::NBI),
+            33 => Ok(IPXEFeature::PXE),
+            34 => Ok(IPXEFeature::ELF),
+            35 => Ok(IPXEFeature::COMBOOT),
+            36 => Ok(IPXEFeature::EFI),
+            37 => Ok(IPXEFeature::FCoE),
+            38 => Ok(IPXEFeature::VLAN),
+            39 => Ok(IPXEFeature::Menu),
+            40 => Ok(IPXEFeature::SDI),
+            41 => Ok(IPXEFeature::NFS),
+            _ => Err(()),
+        }
+    }
+}
+
+impl From<IPXEFeature> for u8 {
+    fn from(item: IPXEFeature) -> Self {
+        match item {
+            IPXEFeature::PXEExt => 16,
+            IPXEFeature::ISCSI => 17,
+            IPXEFeature::AoE => 18,
+            IPXEFeature::HTTP => 19,
+            IPXEFeature::HTTPS => 20,
+            IPXEFeature::TFTP => 21,
+            IPXEFeature::FTP => 22,
+            IPXEFeature::DNS => 23,
+            IPXEFeature::BzImage => 24,
+            IPXEFeature::Multiboot => 25,
+            IPXEFeature::SLAM => 26,
+            IPXEFeature::SRP => 27,
+            IPXEFeature::NBI => 32,
+            IPXEFeature::PXE => 33,
+            IPXEFeature::ELF => 34,
+            IPXEFeature::COMBOOT => 35,
+            IPXEFeature::EFI => 36,
+            IPXEFeature::FCoE => 37,
+            IPXEFeature::VLAN => 38,
+            IPXEFeature::Menu => 39,
+            IPXEFeature::SDI => 40,
+            IPXEFeature::NFS => 41,
+        }
+    }
+}
+
+/// This enum represents the sub-options of option 175 supported by dhcprs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IPXEOption {
+    Priority(i8),             // 1 1 p
+    KeepSAN(bool),            // 8 1 0/1
+    SkipSANBoot(bool),        // 9 1 0/1
+    Feature(IPXEFeature, u8), // 16-41 1 v
+    BusID(u8, u16, u16),      // 177 5 t v1 v2 d1 d2 (bus type, vendor, device)
+    Username(String),         // 190 n u1 u2 ...
+    Password(String),         // 191 n p1 p2 ...
+    Version(u8, u8, u8),      // 235 3 M m p
+
+    // Catchall
+    Option(u8, Vec<u8>),
+}
+
+impl IPXEOption {
+    pub fn from_bytes(bytes: &[u8]) -> Option<Vec<IPXEOption>> {
+        let options = crate::vendor::decode_encapsulated(bytes)?
+            .into_iter()
+            .map(|(code, data)| {
+                let option = match (code, &data[..]) {
+                    (1, [p]) => Some(IPXEOption::Priority(*p as i8)),
+                    (8, [b]) => Some(IPXEOption::KeepSAN(*b != 0)),
+                    (9, [b]) => Some(IPXEOption::SkipSANBoot(*b != 0)),
+                    (177, [t, v1, v2, d1, d2]) => Some(IPXEOption::BusID(
+                        *t,
+                        u16::from_be_bytes([*v1, *v2]),
+                        u16::from_be_bytes([*d1, *d2]),
+                    )),
+                    (190, s) => std::str::from_utf8(s)
+                        .ok()
+                        .map(|s| IPXEOption::Username(s.to_owned())),
+                    (191, s) => std::str::from_utf8(s)
+                        .ok()
+                        .map(|s| IPXEOption::Password(s.to_owned())),
+                    (235, [major, minor, patch]) => {
+                        Some(IPXEOption::Version(*major, *minor, *patch))
+                    }
+                    (code, [v]) => IPXEFeature::try_from(code)
+                        .ok()
+                        .map(|f| IPXEOption::Feature(f, *v)),
+                    _ => None,
+                };
+
+                option.unwrap_or(IPXEOption::Option(code, data))
+            })
+            .collect();
+
+        Some(options)
+    }
+
+    pub fn to_bytes(options: &[IPXEOption]) -> Vec<u8> {
+        let mut bytes: Vec<u8> = Vec::new();
+
+        for option in options {
+            match option {
+                IPXEOption::Priority(p) => {
+                    bytes.push(1);
+                    bytes.push(1);
+                    bytes.push(*p as u8);
+                }
+
+                IPXEOption::KeepSAN(b) => {
+                    bytes.push(8);
+                    bytes.push(1);
+                    bytes.push(*b as u8);
+                }
+
+                IPXEOption::SkipSANBoot(b) => {
+                    bytes.push(9);
+                    bytes.push(1);
+                    bytes.push(*b as u8);
+                }
+
+                IPXEOption::Feature(f, v) => {
+                    bytes.push((*f).into());
+                    bytes.push(1);
+                    bytes.push(*v);
+                }
+
+                IPXEOption::BusID(t, vendor, device) => {
+                    bytes.push(177);
+                    bytes.push(5);
+                    bytes.push(*t);
+                    bytes.extend_from_slice(&vendor.to_be_bytes());
+                    bytes.extend_from_slice(&device.to_be_bytes());
+                }
+
+                IPXEOption::Username(s) => {
+                    bytes.push(190);
+                    bytes.push(s.len() as u8);
+                    bytes.extend_from_slice(s.as_bytes());
+                }
+
+                IPXEOption::Password(s) => {
+                    bytes.push(191);
+                    bytes.push(s.len() as u8);
+                    bytes.extend_from_slice(s.as_bytes());
+                }
+
+                IPXEOption::Version(major, minor, patch) => {
+                    bytes.push(235);
+                    bytes.push(3);
+                    bytes.push(*major);
+                    bytes.push(*minor);
+                    bytes.push(*patch);
+                }
+
+                IPXEOption::Option(n, b) => {
+                    bytes.push(*n);
+                    bytes.push(b.len() as u8);
+                    bytes.extend_from_slice(b);
+                }
+            }
+        }
+
+        bytes
+    }
+}
+
+/// The kind of network boot client a request originates from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BootClient {
+    /// A PXE option ROM or UEFI PXE implementation.
+    PXE,
+    /// iPXE, whether loaded from ROM or chainloaded.
+    IPXE,
+    /// A UEFI HTTP Boot client.
+    HTTPBoot,
+}
+
+/// Classify the boot client a request originates from by its options.
+///
+/// iPXE is detected first as it also identifies itself as a PXE client.
+/// Returns `None` for requests which are not from a network boot client.
+pub fn classify(options: &[DHCPOption]) -> Option<BootClient> {
+    if crate::dhcp::has_user_class(options, b"iPXE") {
+        return Some(BootClient::IPXE);
+    }
+
+    let mut class_identifier: &[u8] = &[];
+    let mut http_architecture = false;
+
+    for option in options {
+        match option {
+            DHCPOption::IPXEEncapsulated(_) => return Some(BootClient::IPXE),
+            DHCPOption::ClassIdentifier(b) => class_identifier = b,
+            DHCPOption::ClientSystemArchitecture(a) => {
+                http_architecture = a.iter().any(|a| a.is_http_boot())
+            }
+            _ => (),
+        }
+    }
+
+    if class_identifier.starts_with(b"HTTPClient") || http_architecture {
+        Some(BootClient::HTTPBoot)
+    } else if class_identifier.starts_with(b"PXEClient") {
+        Some(BootClient::PXE)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dhcp::ClientArchitecture;
+
+    #[test]
+    fn feature_flags() {
+        let bytes = [
+            16, 1, 2, 19, 1, 1, 1, 1, 0xFF, 177, 5, 1, 0x80, 0x86, 0x10, 0x0E, 235, 3, 1, 21, 1,
+            42, 1, 1,
+        ];
+        let options = vec![
+            IPXEOption::Feature(IPXEFeature::PXEExt, 2),
+            IPXEOption::Feature(IPXEFeature::HTTP, 1),
+            IPXEOption::Priority(-1),
+            IPXEOption::BusID(1, 0x8086, 0x100E),
+            IPXEOption::Version(1, 21, 1),
+            IPXEOption::Option(42, vec![1]),
+        ];
+
+        assert_eq!(IPXEOption::from_bytes(&bytes), Some(options.clone()));
+        assert_eq!(IPXEOption::to_bytes(&options), bytes);
+
+        // Features with unexpected lengths are kept raw, truncated data is rejected.
+        assert_eq!(
+            IPXEOption::from_bytes(&[19, 2, 1, 1]),
+            Some(vec![IPXEOption::Option(19, vec![1, 1])])
+        );
+        assert_eq!(IPXEOption::from_bytes(&[19, 2, 1]), None);
+    }
+
+    #[test]
+    fn option_175() {
+        let option = DHCPOption::IPXEEncapsulated(vec![
+            IPXEOption::Feature(IPXEFeature::HTTPS, 1),
+            IPXEOption::Username("user".to_owned()),
+        ]);
+        let bytes = DHCPOption::to_bytes(vec![option.clone(), DHCPOption::End]);
+        assert_eq!(DHCPOption::from_bytes(&bytes[4..]), vec![option]);
+    }
+
+    #[test]
+    fn classification() {
+        let pxe = DHCPOption::ClassIdentifier(b"PXEClient:Arch:00007:UNDI:003016".to_vec());
+        let http = DHCPOption::ClassIdentifier(b"HTTPClient:Arch:00016:UNDI:003001".to_vec());
+        let ipxe = DHCPOption::UserClass(vec![b"iPXE".to_vec()]);
+
+        // The iPXE user class takes precedence over the vendor class iPXE also sends.
+        assert_eq!(
+            classify(&[pxe.clone(), ipxe.clone()]),
+            Some(BootClient::IPXE)
+        );
+        assert_eq!(
+            classify(&[http.clone(), ipxe.clone()]),
+            Some(BootClient::IPXE)
+        );
+        assert_eq!(
+            classify(&[pxe.clone(), DHCPOption::IPXEEncapsulated(Vec::new())]),
+            Some(BootClient::IPXE)
+        );
+
+        let other = DHCPOption::UserClass(vec![b"gPXE".to_vec()]);
+        assert_eq!(classify(&[pxe.clone(), other]), Some(BootClient::PXE));
+        assert_eq!(classify(&[http]), Some(BootClient::HTTPBoot));
+        assert_eq!(
+            classify(&[
+                pxe,
+                DHCPOption::ClientSystemArchitecture(vec![ClientArchitecture::EFIX86_64HTTP])
+            ]),
+            Some(BootClient::HTTPBoot)
+        );
+        assert_eq!(classify(&[ipxe]), Some(BootClient::IPXE));
+        assert_eq!(classify(&[DHCPOption::RapidCommit]), None);
+    }
+}
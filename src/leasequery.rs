@@ -0,0 +1,725 @@
+//! DHCP leasequery (RFC4388) and bulk leasequery (RFC6926) responder.
+//!
+//! Relay agents and access concentrators use leasequery to rebuild their
+//! binding tables, for example after a reboot. Lease state is read from a
+//! `LeaseStore` provided by the server.
+//!
+//! `LeaseQueryResponder::respond` and `LeaseQueryResponder::respond_bulk`
+//! perform no IO, `LeaseQueryResponder::run` and
+//! `LeaseQueryResponder::serve_bulk` are provided to drive them from a
+//! `std::net::UdpSocket` and a TCP connection respectively.
+
+use crate::bootp::{BOOTPPacket, OpCode, RawBOOTPPacket};
+use crate::dhcp::{DHCPMessageType, DHCPOption, DHCPState, StatusCode};
+use eui48::MacAddress;
+use std::io::{Read, Write};
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4, UdpSocket};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// TCP port bulk leasequery connections are made to.
+pub const BULK_LEASEQUERY_PORT: u16 = 67;
+
+/// A binding held by a `LeaseStore`.
+///
+/// Times are in seconds since the Unix epoch.
+#[derive(Debug, Clone)]
+pub struct LeaseInfo {
+    pub address: Ipv4Addr,
+    pub chaddr: Option<MacAddress>,
+    pub client_identifier: Option<Vec<u8>>,
+    pub state: DHCPState,
+    /// When the lease ends, `None` for leases which do not expire.
+    pub expires: Option<u32>,
+    /// When the client last sent a message for this lease.
+    pub last_transaction: u32,
+    /// When the binding entered its current state.
+    pub state_start: u32,
+    /// The relay agent information option (82) data received with the lease.
+    pub relay_agent_information: Option<Vec<u8>>,
+}
+
+/// Source of the bindings a `LeaseQueryResponder` answers from.
+pub trait LeaseStore {
+    /// The binding for `address`, if there is one.
+    fn lease_by_address(&self, address: Ipv4Addr) -> Option<LeaseInfo>;
+
+    /// Whether the server is responsible for assigning `address`.
+    ///
+    /// Queries for such addresses without a binding are answered with
+    /// DHCPLEASEUNASSIGNED rather than DHCPLEASEUNKNOWN.
+    fn is_authoritative(&self, address: Ipv4Addr) -> bool;
+
+    /// All bindings held by the client with hardware address `chaddr`.
+    fn leases_by_hardware_address(&self, chaddr: MacAddress) -> Vec<LeaseInfo>;
+
+    /// All bindings held by the client with client identifier `client_identifier`.
+    fn leases_by_client_identifier(&self, client_identifier: &[u8]) -> Vec<LeaseInfo>;
+
+    /// Every binding, used for bulk leasequeries which do not name a client.
+    fn leases(&self) -> Vec<LeaseInfo>;
+}
+
+enum Query {
+    Address(Ipv4Addr),
+    HardwareAddress(MacAddress),
+    ClientIdentifier(Vec<u8>),
+    All,
+}
+
+fn unix_time() -> u32 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs() as u32)
+}
+
+pub struct LeaseQueryResponder<S: LeaseStore> {
+    store: S,
+    server_identifier: Ipv4Addr,
+}
+
+impl<S: LeaseStore> LeaseQueryResponder<S> {
+    /// Create a new responder answering from `store` with the server identifier `server_identifier`.
+    pub fn new(store: S, server_identifier: Ipv4Addr) -> Self {
+        Self {
+            store,
+            server_identifier,
+        }
+    }
+
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+
+    fn query(request: &BOOTPPacket, options: &[DHCPOption]) -> Query {
+        let client_identifier = options.iter().find_map(|o| match o {
+            DHCPOption::ClientIdentifier(b) => Some(b.clone()),
+            _ => None,
+        });
+
+        match (request.ciaddr, client_identifier) {
+            (Some(address), _) => Query::Address(address),
+            (None, Some(client_identifier)) => Query::ClientIdentifier(client_identifier),
+            (None, None) if !request.chaddr.is_nil() => Query::HardwareAddress(request.chaddr),
+            (None, None) => Query::All,
+        }
+    }
+
+    fn reply(
+        &self,
+        request: &BOOTPPacket,
+        message_type: DHCPMessageType,
+        mut options: Vec<DHCPOption>,
+    ) -> BOOTPPacket {
+        options.insert(0, DHCPOption::ServerIdentifier(self.server_identifier));
+        options.insert(0, DHCPOption::DHCPMessageType(message_type));
+        options.push(DHCPOption::End);
+
+        let mut reply = request.reply();
+        if reply.set_options(options).is_err() {
+            // Drop everything but the message type and server identifier rather than not answering.
+            reply
+                .set_options(vec![
+                    DHCPOption::DHCPMessageType(message_type),
+                    DHCPOption::ServerIdentifier(self.server_identifier),
+                    DHCPOption::End,
+                ])
+                .unwrap();
+        }
+        reply
+    }
+
+    // A DHCPLEASEACTIVE (or DHCPLEASEUNASSIGNED for bindings which are not
+    // active) describing `lease`, with `associated` as option 92.
+    fn lease_reply(
+        &self,
+        request: &BOOTPPacket,
+        parameter_request: &[u8],
+        lease: &LeaseInfo,
+        associated: Vec<Ipv4Addr>,
+        now: u32,
+    ) -> BOOTPPacket {
+        let requested = |code: u8| parameter_request.contains(&code);
+        let mut options: Vec<DHCPOption> = Vec::new();
+
+        let message_type = if lease.state == DHCPState::Active {
+            if let Some(expires) = lease.expires {
+                options.push(DHCPOption::IPAddressLeaseTime(expires.saturating_sub(now)));
+            } else {
+                options.push(DHCPOption::IPAddressLeaseTime(u32::MAX));
+            }
+            DHCPMessageType::DHCPLeaseActive
+        } else {
+            DHCPMessageType::DHCPLeaseUnassigned
+        };
+
+        options.push(DHCPOption::ClientLastTransactionTime(
+            now.saturating_sub(lease.last_transaction),
+        ));
+        if let Some(client_identifier) = &lease.client_identifier {
+            options.push(DHCPOption::ClientIdentifier(client_identifier.clone()));
+        }
+        if !associated.is_empty() {
+            options.push(DHCPOption::AssociatedIP(associated));
+        }
+        if let Some(relay_agent_information) = &lease.relay_agent_information {
+            if requested(82) {
+                options.push(DHCPOption::Option(82, relay_agent_information.clone()));
+            }
+        }
+        if requested(152) {
+            options.push(DHCPOption::BaseTime(now));
+        }
+        if requested(153) {
+            options.push(DHCPOption::StartTimeOfState(
+                now.saturating_sub(lease.state_start),
+            ));
+        }
+        if requested(156) {
+            options.push(DHCPOption::DHCPState(lease.state));
+        }
+
+        let mut reply = self.reply(request, message_type, options);
+        reply.ciaddr = Some(lease.address);
+        if let Some(chaddr) = lease.chaddr {
+            reply.chaddr = chaddr;
+        }
+        reply
+    }
+
+    // The reply to a query for a client, the most recent binding is
+    // described and the others listed as associated addresses.
+    fn client_reply(
+        &self,
+        request: &BOOTPPacket,
+        parameter_request: &[u8],
+        mut leases: Vec<LeaseInfo>,
+        now: u32,
+    ) -> BOOTPPacket {
+        leases.sort_by_key(|l| std::cmp::Reverse(l.last_transaction));
+        match leases.split_first() {
+            Some((lease, others)) => {
+                let associated = others.iter().map(|l| l.address).collect();
+                self.lease_reply(request, parameter_request, lease, associated, now)
+            }
+            None => self.reply(request, DHCPMessageType::DHCPLeaseUnknown, Vec::new()),
+        }
+    }
+
+    /// Respond to a DHCPLEASEQUERY received at `now`, in seconds since the Unix epoch.
+    ///
+    /// Queries by address are answered with DHCPLEASEACTIVE,
+    /// DHCPLEASEUNASSIGNED or DHCPLEASEUNKNOWN depending on the binding.
+    /// Queries by client identifier or hardware address are answered with
+    /// DHCPLEASEACTIVE for the most recent binding, listing the addresses of
+    /// the others in option 92, or DHCPLEASEUNKNOWN. Queries naming no client
+    /// are answered with DHCPLEASEUNKNOWN carrying a MalformedQuery status
+    /// code. Returns `None` for anything which is not a DHCPLEASEQUERY.
+    pub fn respond(&self, request: &BOOTPPacket, now: u32) -> Option<BOOTPPacket> {
+        if !matches!(request.op, OpCode::BOOTREQUEST) {
+            return None;
+        }
+
+        let options = request.get_options();
+        let mut message_type: Option<DHCPMessageType> = None;
+        let mut parameter_request: &[u8] = &[];
+        for option in &options {
+            match option {
+                DHCPOption::DHCPMessageType(t) => message_type = Some(*t),
+                DHCPOption::ParameterRequest(b) => parameter_request = b,
+                _ => (),
+            }
+        }
+
+        if message_type? != DHCPMessageType::DHCPLeaseQuery {
+            return None;
+        }
+
+        let reply = match Self::query(request, &options) {
+            Query::Address(address) => match self.store.lease_by_address(address) {
+                Some(lease) => {
+                    self.lease_reply(request, parameter_request, &lease, Vec::new(), now)
+                }
+                None => {
+                    let message_type = if self.store.is_authoritative(address) {
+                        DHCPMessageType::DHCPLeaseUnassigned
+                    } else {
+                        DHCPMessageType::DHCPLeaseUnknown
+                    };
+                    let mut reply = self.reply(request, message_type, Vec::new());
+                    reply.ciaddr = Some(address);
+                    reply
+                }
+            },
+            Query::HardwareAddress(chaddr) => self.client_reply(
+                request,
+                parameter_request,
+                self.store.leases_by_hardware_address(chaddr),
+                now,
+            ),
+            Query::ClientIdentifier(client_identifier) => self.client_reply(
+                request,
+                parameter_request,
+                self.store.leases_by_client_identifier(&client_identifier),
+                now,
+            ),
+            // A leasequery must name a client, tell the requestor its query was malformed.
+            Query::All => self.reply(
+                request,
+                DHCPMessageType::DHCPLeaseUnknown,
+                vec![DHCPOption::StatusCode(
+                    StatusCode::MalformedQuery,
+                    "no ciaddr, chaddr or client identifier".to_owned(),
+                )],
+            ),
+        };
+
+        Some(reply)
+    }
+
+    /// Respond to a DHCPBULKLEASEQUERY received at `now`, in seconds since the Unix epoch.
+    ///
+    /// One reply is returned for each matching binding, followed by a
+    /// DHCPLEASEQUERYDONE. Queries which do not name an address, client
+    /// identifier or hardware address match every binding, filtered by the
+    /// query start and end times if present. Malformed queries are answered
+    /// with only a DHCPLEASEQUERYDONE carrying a status code.
+    pub fn respond_bulk(&self, request: &BOOTPPacket, now: u32) -> Vec<BOOTPPacket> {
+        let options = request.get_options();
+        let mut message_type: Option<DHCPMessageType> = None;
+        let mut parameter_request: &[u8] = &[];
+        let mut query_start: Option<u32> = None;
+        let mut query_end: Option<u32> = None;
+        for option in &options {
+            match option {
+                DHCPOption::DHCPMessageType(t) => message_type = Some(*t),
+                DHCPOption::ParameterRequest(b) => parameter_request = b,
+                DHCPOption::QueryStartTime(t) => query_start = Some(*t),
+                DHCPOption::QueryEndTime(t) => query_end = Some(*t),
+                _ => (),
+            }
+        }
+
+        if !matches!(request.op, OpCode::BOOTREQUEST)
+            || message_type != Some(DHCPMessageType::DHCPBulkLeaseQuery)
+        {
+            return vec![self.reply(
+                request,
+                DHCPMessageType::DHCPLeaseQueryDone,
+                vec![DHCPOption::StatusCode(
+                    StatusCode::MalformedQuery,
+                    "expected DHCPBULKLEASEQUERY".to_owned(),
+                )],
+            )];
+        }
+
+        let mut replies = match Self::query(request, &options) {
+            Query::Address(address) => self
+                .store
+                .lease_by_address(address)
+                .map(|l| self.lease_reply(request, parameter_request, &l, Vec::new(), now))
+                .into_iter()
+                .collect(),
+            Query::HardwareAddress(chaddr) => {
+                let leases = self.store.leases_by_hardware_address(chaddr);
+                if leases.is_empty() {
+                    Vec::new()
+                } else {
+                    vec![self.client_reply(request, parameter_request, leases, now)]
+                }
+            }
+            Query::ClientIdentifier(client_identifier) => {
+                let leases = self.store.leases_by_client_identifier(&client_identifier);
+                if leases.is_empty() {
+                    Vec::new()
+                } else {
+                    vec![self.client_reply(request, parameter_request, leases, now)]
+                }
+            }
+            Query::All => self
+                .store
+                .leases()
+                .iter()
+                .filter(|l| query_start.is_none_or(|t| l.state_start >= t))
+                .filter(|l| query_end.is_none_or(|t| l.state_start <= t))
+                .map(|l| self.lease_reply(request, parameter_request, l, Vec::new(), now))
+                .collect(),
+        };
+
+        replies.push(self.reply(
+            request,
+            DHCPMessageType::DHCPLeaseQueryDone,
+            vec![DHCPOption::StatusCode(StatusCode::Success, String::new())],
+        ));
+        replies
+    }
+
+    /// Answer leasequeries received on `socket` until an IO error occurs.
+    ///
+    /// Replies are sent to the relay agent address of the query, or the
+    /// source of the query if it has none.
+    pub fn run(&self, socket: &UdpSocket) -> std::io::Result<()> {
+        let mut buffer: [u8; 1500] = [0; 1500];
+
+        loop {
+            let (length, source) = socket.recv_from(&mut buffer)?;
+            let source = match source {
+                SocketAddr::V4(s) => s,
+                SocketAddr::V6(_) => continue,
+            };
+
+            let request: BOOTPPacket = match RawBOOTPPacket::from_bytes(&buffer[..length]) {
+                Some(raw) => raw.into(),
+                None => continue,
+            };
+
+            if let Some(reply) = self.respond(&request, unix_time()) {
+                let destination = match request.giaddr {
+                    Some(giaddr) => SocketAddrV4::new(giaddr, 67),
+                    None => source,
+                };
+                let raw: RawBOOTPPacket = reply.into();
+                socket.send_to(raw.as_bytes(), destination)?;
+            }
+        }
+    }
+
+    /// Answer bulk leasequeries received on `stream` until the requestor closes it.
+    pub fn serve_bulk<T: Read + Write>(&self, stream: &mut T) -> std::io::Result<()> {
+        while let Some(request) = read_message(stream)? {
+            for reply in self.respond_bulk(&request, unix_time()) {
+                write_message(stream, reply)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Read a DHCP message framed for a bulk leasequery TCP connection.
+///
+/// Messages are preceded by their length as two bytes in network byte order.
+/// Returns `None` when the connection has been closed between messages.
+pub fn read_message<R: Read>(reader: &mut R) -> std::io::Result<Option<BOOTPPacket>> {
+    let mut length: [u8; 2] = [0; 2];
+    match reader.read_exact(&mut length) {
+        Ok(()) => (),
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e),
+    }
+
+    let mut buffer = vec![0; u16::from_be_bytes(length) as usize];
+    reader.read_exact(&mut buffer)?;
+
+    match RawBOOTPPacket::from_bytes(&buffer) {
+        Some(raw) => Ok(Some(raw.into())),
+        None => Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "invalid DHCP message",
+        )),
+    }
+}
+
+/// Write a DHCP message framed for a bulk leasequery TCP connection.
+pub fn write_message<W: Write>(writer: &mut W, packet: BOOTPPacket) -> std::io::Result<()> {
+    let raw: RawBOOTPPacket = packet.into();
+    let bytes = raw.as_bytes();
+
+    writer.write_all(&(bytes.len() as u16).to_be_bytes())?;
+    writer.write_all(bytes)?;
+    writer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SERVER: Ipv4Addr = Ipv4Addr::new(192, 0, 2, 1);
+    const CURRENT: Ipv4Addr = Ipv4Addr::new(192, 0, 2, 10);
+    const PREVIOUS: Ipv4Addr = Ipv4Addr::new(192, 0, 2, 11);
+    const CHADDR: [u8; 6] = [2, 0, 0, 0, 0, 1];
+    const CLIENT_IDENTIFIER: &[u8] = b"\x01client";
+    const NOW: u32 = 1000;
+
+    struct Store(Vec<LeaseInfo>);
+
+    impl LeaseStore for Store {
+        fn lease_by_address(&self, address: Ipv4Addr) -> Option<LeaseInfo> {
+            self.0.iter().find(|l| l.address == address).cloned()
+        }
+
+        fn is_authoritative(&self, address: Ipv4Addr) -> bool {
+            address.octets()[..3] == [192, 0, 2]
+        }
+
+        fn leases_by_hardware_address(&self, chaddr: MacAddress) -> Vec<LeaseInfo> {
+            self.0
+                .iter()
+                .filter(|l| l.chaddr == Some(chaddr))
+                .cloned()
+                .collect()
+        }
+
+        fn leases_by_client_identifier(&self, client_identifier: &[u8]) -> Vec<LeaseInfo> {
+            self.0
+                .iter()
+                .filter(|l| l.client_identifier.as_deref() == Some(client_identifier))
+                .cloned()
+                .collect()
+        }
+
+        fn leases(&self) -> Vec<LeaseInfo> {
+            self.0.clone()
+        }
+    }
+
+    fn responder() -> LeaseQueryResponder<Store> {
+        let lease =
+            |address, client_identifier: Option<&[u8]>, state, last_transaction| LeaseInfo {
+                address,
+                chaddr: Some(MacAddress::new(CHADDR)),
+                client_identifier: client_identifier.map(|c| c.to_vec()),
+                state,
+                expires: Some(NOW + 600),
+                last_transaction,
+                state_start: last_transaction,
+                relay_agent_information: None,
+            };
+
+        let store = Store(vec![
+            lease(PREVIOUS, None, DHCPState::Expired, 500),
+            lease(CURRENT, Some(CLIENT_IDENTIFIER), DHCPState::Active, 900),
+        ]);
+        LeaseQueryResponder::new(store, SERVER)
+    }
+
+    fn query(
+        message_type: DHCPMessageType,
+        ciaddr: Option<Ipv4Addr>,
+        chaddr: MacAddress,
+        mut options: Vec<DHCPOption>,
+    ) -> BOOTPPacket {
+        let mut request = BOOTPPacket::new(
+            OpCode::BOOTREQUEST,
+            0,
+            1,
+            0,
+            0,
+            ciaddr,
+            None,
+            None,
+            Some(Ipv4Addr::new(10, 0, 0, 1)),
+            chaddr,
+            [0; 64],
+            [0; 128],
+            [0; 312],
+        );
+        options.insert(0, DHCPOption::DHCPMessageType(message_type));
+        options.push(DHCPOption::End);
+        request.set_options(options).unwrap();
+        request
+    }
+
+    fn message_type(packet: &BOOTPPacket) -> Option<DHCPMessageType> {
+        packet.get_options().iter().find_map(|o| match o {
+            DHCPOption::DHCPMessageType(t) => Some(*t),
+            _ => None,
+        })
+    }
+
+    #[test]
+    fn query_by_address() {
+        let responder = responder();
+        let leasequery = |address| {
+            let request = query(
+                DHCPMessageType::DHCPLeaseQuery,
+                Some(address),
+                MacAddress::nil(),
+                vec![DHCPOption::ParameterRequest(vec![156])],
+            );
+            responder.respond(&request, NOW).unwrap()
+        };
+
+        let reply = leasequery(CURRENT);
+        assert_eq!(message_type(&reply), Some(DHCPMessageType::DHCPLeaseActive));
+        assert_eq!(reply.ciaddr, Some(CURRENT));
+        assert_eq!(reply.chaddr, MacAddress::new(CHADDR));
+        let options = reply.get_options();
+        assert!(options.contains(&DHCPOption::IPAddressLeaseTime(600)));
+        assert!(options.contains(&DHCPOption::ClientLastTransactionTime(100)));
+        assert!(options.contains(&DHCPOption::DHCPState(DHCPState::Active)));
+
+        let reply = leasequery(PREVIOUS);
+        assert_eq!(
+            message_type(&reply),
+            Some(DHCPMessageType::DHCPLeaseUnassigned)
+        );
+        assert!(reply
+            .get_options()
+            .contains(&DHCPOption::DHCPState(DHCPState::Expired)));
+
+        // Without a binding, only addresses the server assigns are unassigned.
+        let reply = leasequery(Ipv4Addr::new(192, 0, 2, 20));
+        assert_eq!(
+            message_type(&reply),
+            Some(DHCPMessageType::DHCPLeaseUnassigned)
+        );
+        let reply = leasequery(Ipv4Addr::new(198, 51, 100, 1));
+        assert_eq!(
+            message_type(&reply),
+            Some(DHCPMessageType::DHCPLeaseUnknown)
+        );
+        assert_eq!(reply.ciaddr, Some(Ipv4Addr::new(198, 51, 100, 1)));
+    }
+
+    #[test]
+    fn query_by_client() {
+        let responder = responder();
+
+        // The most recent binding is described, the others are associated addresses.
+        let request = query(
+            DHCPMessageType::DHCPLeaseQuery,
+            None,
+            MacAddress::new(CHADDR),
+            Vec::new(),
+        );
+        let reply = responder.respond(&request, NOW).unwrap();
+        assert_eq!(message_type(&reply), Some(DHCPMessageType::DHCPLeaseActive));
+        assert_eq!(reply.ciaddr, Some(CURRENT));
+        assert!(reply
+            .get_options()
+            .contains(&DHCPOption::AssociatedIP(vec![PREVIOUS])));
+
+        let request = query(
+            DHCPMessageType::DHCPLeaseQuery,
+            None,
+            MacAddress::nil(),
+            vec![DHCPOption::ClientIdentifier(CLIENT_IDENTIFIER.to_vec())],
+        );
+        let reply = responder.respond(&request, NOW).unwrap();
+        assert_eq!(message_type(&reply), Some(DHCPMessageType::DHCPLeaseActive));
+        assert_eq!(reply.ciaddr, Some(CURRENT));
+        assert!(reply
+            .get_options()
+            .contains(&DHCPOption::ClientIdentifier(CLIENT_IDENTIFIER.to_vec())));
+
+        let request = query(
+            DHCPMessageType::DHCPLeaseQuery,
+            None,
+            MacAddress::new([2, 0, 0, 0, 0, 2]),
+            Vec::new(),
+        );
+        let reply = responder.respond(&request, NOW).unwrap();
+        assert_eq!(
+            message_type(&reply),
+            Some(DHCPMessageType::DHCPLeaseUnknown)
+        );
+    }
+
+    #[test]
+    fn malformed_query() {
+        let responder = responder();
+
+        let request = query(
+            DHCPMessageType::DHCPLeaseQuery,
+            None,
+            MacAddress::nil(),
+            Vec::new(),
+        );
+        let reply = responder.respond(&request, NOW).unwrap();
+        assert_eq!(
+            message_type(&reply),
+            Some(DHCPMessageType::DHCPLeaseUnknown)
+        );
+        assert!(reply
+            .get_options()
+            .iter()
+            .any(|o| matches!(o, DHCPOption::StatusCode(StatusCode::MalformedQuery, _))));
+
+        let request = query(
+            DHCPMessageType::DHCPRequest,
+            Some(CURRENT),
+            MacAddress::nil(),
+            Vec::new(),
+        );
+        assert!(responder.respond(&request, NOW).is_none());
+    }
+
+    #[test]
+    fn bulk_leasequery() {
+        let responder = responder();
+        let done = DHCPOption::StatusCode(StatusCode::Success, String::new());
+
+        let request = query(
+            DHCPMessageType::DHCPBulkLeaseQuery,
+            None,
+            MacAddress::nil(),
+            vec![DHCPOption::QueryStartTime(600)],
+        );
+        let replies = responder.respond_bulk(&request, NOW);
+        assert_eq!(replies.len(), 2);
+        assert_eq!(replies[0].ciaddr, Some(CURRENT));
+        assert_eq!(
+            message_type(&replies[1]),
+            Some(DHCPMessageType::DHCPLeaseQueryDone)
+        );
+        assert!(replies[1].get_options().contains(&done));
+
+        let request = query(
+            DHCPMessageType::DHCPBulkLeaseQuery,
+            None,
+            MacAddress::new([2, 0, 0, 0, 0, 2]),
+            Vec::new(),
+        );
+        let replies = responder.respond_bulk(&request, NOW);
+        assert_eq!(replies.len(), 1);
+        assert!(replies[0].get_options().contains(&done));
+
+        let request = query(
+            DHCPMessageType::DHCPLeaseQuery,
+            None,
+            MacAddress::nil(),
+            Vec::new(),
+        );
+        let replies = responder.respond_bulk(&request, NOW);
+        assert_eq!(replies.len(), 1);
+        assert_eq!(
+            message_type(&replies[0]),
+            Some(DHCPMessageType::DHCPLeaseQueryDone)
+        );
+        assert!(replies[0]
+            .get_options()
+            .iter()
+            .any(|o| matches!(o, DHCPOption::StatusCode(StatusCode::MalformedQuery, _))));
+    }
+
+    #[test]
+    fn framing() {
+        let request = query(
+            DHCPMessageType::DHCPBulkLeaseQuery,
+            Some(CURRENT),
+            MacAddress::new(CHADDR),
+            Vec::new(),
+        );
+
+        let mut stream: Vec<u8> = Vec::new();
+        write_message(&mut stream, request.clone()).unwrap();
+        write_message(&mut stream, request).unwrap();
+        let length = u16::from_be_bytes([stream[0], stream[1]]) as usize;
+        assert_eq!(stream.len(), 2 * (length + 2));
+
+        let mut reader = &stream[..];
+        for _ in 0..2 {
+            let message = read_message(&mut reader).unwrap().unwrap();
+            assert_eq!(message.ciaddr, Some(CURRENT));
+            assert_eq!(message.chaddr, MacAddress::new(CHADDR));
+            assert_eq!(
+                message_type(&message),
+                Some(DHCPMessageType::DHCPBulkLeaseQuery)
+            );
+        }
+        assert!(read_message(&mut reader).unwrap().is_none());
+
+        // A connection closed part way through a message is an error.
+        assert!(read_message(&mut &stream[..length]).is_err());
+    }
+}
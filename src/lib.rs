@@ -6,10 +6,35 @@
 //!
 //! BOOTP specific functionality is provided by the `dhcprs::bootp` module
 //! DHCP specific functionality is provided by the `dhcprs::dhcp` module
+//! DHCP authentication (option 90) is provided by the `dhcprs::auth` module
+//! Encrypted DNS resolver discovery (option 162) is provided by the `dhcprs::dnr` module
+//! Decoding of vendor specific information is provided by the `dhcprs::vendor` module
+//! PXE vendor specific sub-options are provided by the `dhcprs::pxe` module
+//! iPXE encapsulated options and boot client classification are provided by the `dhcprs::ipxe` module
+//! UEFI HTTP Boot replies are provided by the `dhcprs::httpboot` module
+//! A proxyDHCP and PXE boot server responder is provided by the `dhcprs::proxydhcp` module
+//! A DHCP client state machine is provided by the `dhcprs::client` module
+//! FORCERENEW with nonce authentication is provided by the `dhcprs::forcerenew` module
+//! A leasequery and bulk leasequery responder is provided by the `dhcprs::leasequery` module
+//! Server side helpers such as link selection are provided by the `dhcprs::server` module
+//! A read-only TFTP server for network booting is provided by the `dhcprs::tftp` module
 //!
 //! There is additionally simple UDP packet encode/decode provided by the
 //! `dhcprs::udpbuilder`.
 
+pub mod auth;
 pub mod bootp;
+pub mod client;
 pub mod dhcp;
+pub mod dnr;
+mod dns;
+pub mod forcerenew;
+pub mod httpboot;
+pub mod ipxe;
+pub mod leasequery;
+pub mod proxydhcp;
+pub mod pxe;
+pub mod server;
+pub mod tftp;
 pub mod udpbuilder;
+pub mod vendor;
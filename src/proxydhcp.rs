@@ -0,0 +1,374 @@
+//! ProxyDHCP and PXE boot server responder.
+//!
+//! A proxyDHCP server runs alongside an existing DHCP server and answers only
+//! PXE clients, providing boot server and boot file information without
+//! assigning addresses. The same responder answers the follow-up boot server
+//! discovery on UDP port 4011.
+//!
+//! `ProxyDHCP::respond` performs no IO, `ProxyDHCP::run` is provided to drive
+//! it from a `std::net::UdpSocket`.
+
+use crate::bootp::{BOOTPPacket, OpCode, RawBOOTPPacket};
+use crate::dhcp::{ClientArchitecture, DHCPMessageType, DHCPOption};
+use crate::pxe::{PXEDiscoveryControl, PXEOption};
+use crate::vendor::VendorInformation;
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4, UdpSocket};
+
+/// UDP port proxyDHCP offers are made on, shared with the DHCP server.
+pub const PROXYDHCP_PORT: u16 = 67;
+
+/// UDP port of the PXE boot server.
+pub const PXE_BOOT_SERVER_PORT: u16 = 4011;
+
+pub struct ProxyDHCP {
+    server_identifier: Ipv4Addr,
+    tftp_server: Ipv4Addr,
+    bootfile: String,
+    architecture_bootfiles: Vec<(ClientArchitecture, String)>,
+}
+
+impl ProxyDHCP {
+    /// Create a new proxyDHCP responder.
+    ///
+    /// Replies are sent from `server_identifier` and direct clients to
+    /// download `bootfile` from the TFTP server at `tftp_server`.
+    pub fn new(server_identifier: Ipv4Addr, tftp_server: Ipv4Addr, bootfile: &str) -> Self {
+        Self {
+            server_identifier,
+            tftp_server,
+            bootfile: bootfile.to_owned(),
+            architecture_bootfiles: Vec::new(),
+        }
+    }
+
+    /// Use a different boot file for clients reporting the given architecture in option 93.
+    pub fn set_architecture_bootfile(&mut self, architecture: ClientArchitecture, bootfile: &str) {
+        self.architecture_bootfiles
+            .retain(|(a, _)| *a != architecture);
+        self.architecture_bootfiles
+            .push((architecture, bootfile.to_owned()));
+    }
+
+    fn bootfile(&self, architectures: &[ClientArchitecture]) -> &str {
+        architectures
+            .iter()
+            .find_map(|architecture| {
+                self.architecture_bootfiles
+                    .iter()
+                    .find(|(a, _)| a == architecture)
+            })
+            .map_or(&self.bootfile, |(_, bootfile)| bootfile)
+    }
+
+    /// Respond to a request received on `local_port`.
+    ///
+    /// DHCPDISCOVERs on port 67 are answered with a proxyDHCP DHCPOFFER and
+    /// DHCPREQUESTs on port 4011 with a DHCPACK. Returns `None` if the request
+    /// should be ignored, which includes every request not from a PXE client.
+    pub fn respond(&self, request: &BOOTPPacket, local_port: u16) -> Option<BOOTPPacket> {
+        if !matches!(request.op, OpCode::BOOTREQUEST) {
+            return None;
+        }
+
+        let mut message_type: Option<&DHCPMessageType> = None;
+        let mut class_identifier: &[u8] = &[];
+        let mut server_identifier: Option<Ipv4Addr> = None;
+        let mut architectures: &[ClientArchitecture] = &[];
+        let mut client_uuid: Option<&DHCPOption> = None;
+        let mut boot_item: Option<(u16, u16)> = None;
+
+        let options = request.get_options();
+        for option in &options {
+            match option {
+                DHCPOption::DHCPMessageType(t) => message_type = Some(t),
+                DHCPOption::ClassIdentifier(b) => class_identifier = b,
+                DHCPOption::ServerIdentifier(addr) => server_identifier = Some(*addr),
+                DHCPOption::ClientSystemArchitecture(a) => architectures = a,
+                // Echoed as sent, including identifiers with a type other than 0.
+                DHCPOption::ClientMachineIdentifier(_) | DHCPOption::Option(97, _) => {
+                    client_uuid = Some(option)
+                }
+                DHCPOption::VendorSpecificInformation(b) => {
+                    boot_item = PXEOption::from_bytes(b)
+                        .unwrap_or_default()
+                        .into_iter()
+                        .find_map(|o| match o {
+                            PXEOption::BootItem(t, l) => Some((t, l)),
+                            _ => None,
+                        })
+                }
+                _ => (),
+            }
+        }
+
+        if !class_identifier.starts_with(b"PXEClient") {
+            return None;
+        }
+
+        let (reply_type, vendor_options) = match (local_port, message_type?) {
+            (PROXYDHCP_PORT, DHCPMessageType::DHCPDiscover) => (
+                DHCPMessageType::DHCPOffer,
+                vec![PXEOption::DiscoveryControl(PXEDiscoveryControl {
+                    disable_multicast: true,
+                    use_bootfile: true,
+                    ..Default::default()
+                })],
+            ),
+
+            // Only answer requests on port 67 if the client selected our offer.
+            (PROXYDHCP_PORT, DHCPMessageType::DHCPRequest)
+                if server_identifier == Some(self.server_identifier) =>
+            {
+                (DHCPMessageType::DHCPACK, Vec::new())
+            }
+
+            (PXE_BOOT_SERVER_PORT, DHCPMessageType::DHCPRequest | DHCPMessageType::DHCPInform) => {
+                let (server_type, layer) = boot_item.unwrap_or((0, 0));
+                (
+                    DHCPMessageType::DHCPACK,
+                    vec![PXEOption::BootItem(server_type, layer)],
+                )
+            }
+
+            _ => return None,
+        };
+
+        let bootfile = self.bootfile(architectures);
+
+        let mut reply_options = vec![
+            DHCPOption::DHCPMessageType(reply_type),
+            DHCPOption::ServerIdentifier(self.server_identifier),
+            DHCPOption::ClassIdentifier(b"PXEClient".to_vec()),
+        ];
+        if let Some(uuid) = client_uuid {
+            reply_options.push(uuid.clone());
+        }
+        if !vendor_options.is_empty() {
+            reply_options.push(VendorInformation::PXE(vendor_options).into());
+        }
+        reply_options.push(DHCPOption::TFTPServerName(self.tftp_server.to_string()));
+        reply_options.push(DHCPOption::BootfileName(bootfile.to_owned()));
+        reply_options.push(DHCPOption::End);
+
+        let mut reply = request.reply();
+        reply.siaddr = Some(self.tftp_server);
+        reply.set_file(bootfile);
+        reply.set_options(reply_options).ok()?;
+
+        Some(reply)
+    }
+
+    /// Address the response to `request`, received from `source` on `local_port`, should be sent to.
+    pub fn destination(
+        request: &BOOTPPacket,
+        source: SocketAddrV4,
+        local_port: u16,
+    ) -> SocketAddrV4 {
+        if local_port == PXE_BOOT_SERVER_PORT {
+            return source;
+        }
+
+        match (request.giaddr, request.ciaddr) {
+            (Some(giaddr), _) => SocketAddrV4::new(giaddr, 67),
+            (None, Some(ciaddr)) => SocketAddrV4::new(ciaddr, 68),
+            (None, None) => SocketAddrV4::new(Ipv4Addr::BROADCAST, 68),
+        }
+    }
+
+    /// Answer requests received on `socket` until an IO error occurs.
+    ///
+    /// The socket should be bound to port 67 or 4011 and have broadcast
+    /// enabled. Run one socket per port to serve both.
+    pub fn run(&self, socket: &UdpSocket) -> std::io::Result<()> {
+        let local_port = socket.local_addr()?.port();
+        let mut buffer: [u8; 1500] = [0; 1500];
+
+        loop {
+            let (length, source) = socket.recv_from(&mut buffer)?;
+            let source = match source {
+                SocketAddr::V4(s) => s,
+                SocketAddr::V6(_) => continue,
+            };
+
+            let request: BOOTPPacket = match RawBOOTPPacket::from_bytes(&buffer[..length]) {
+                Some(raw) => raw.into(),
+                None => continue,
+            };
+
+            if let Some(reply) = self.respond(&request, local_port) {
+                let destination = Self::destination(&request, source, local_port);
+                let raw: RawBOOTPPacket = reply.into();
+                socket.send_to(raw.as_bytes(), destination)?;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use eui48::MacAddress;
+
+    const SERVER: Ipv4Addr = Ipv4Addr::new(192, 0, 2, 1);
+    const TFTP_SERVER: Ipv4Addr = Ipv4Addr::new(192, 0, 2, 2);
+    const UUID: [u8; 16] = [7; 16];
+    const PXE_CLIENT: &[u8] = b"PXEClient:Arch:00007:UNDI:003016";
+
+    fn proxy() -> ProxyDHCP {
+        let mut proxy = ProxyDHCP::new(SERVER, TFTP_SERVER, "undionly.kpxe");
+        proxy.set_architecture_bootfile(ClientArchitecture::EFIX86_64, "ipxe.efi");
+        proxy
+    }
+
+    fn request(message_type: DHCPMessageType, mut options: Vec<DHCPOption>) -> BOOTPPacket {
+        let mut request = BOOTPPacket::new(
+            OpCode::BOOTREQUEST,
+            0,
+            1,
+            0,
+            0,
+            None,
+            None,
+            None,
+            None,
+            MacAddress::new([2, 0, 0, 0, 0, 1]),
+            [0; 64],
+            [0; 128],
+            [0; 312],
+        );
+        options.insert(0, DHCPOption::DHCPMessageType(message_type));
+        options.push(DHCPOption::End);
+        request.set_options(options).unwrap();
+        request
+    }
+
+    fn pxe_options(options: &[DHCPOption]) -> Option<Vec<PXEOption>> {
+        options.iter().find_map(|o| match o {
+            DHCPOption::VendorSpecificInformation(b) => PXEOption::from_bytes(b),
+            _ => None,
+        })
+    }
+
+    #[test]
+    fn offer() {
+        let discover = request(
+            DHCPMessageType::DHCPDiscover,
+            vec![
+                DHCPOption::ClassIdentifier(PXE_CLIENT.to_vec()),
+                DHCPOption::ClientSystemArchitecture(vec![ClientArchitecture::EFIX86_64]),
+                DHCPOption::ClientMachineIdentifier(UUID),
+            ],
+        );
+        let offer = proxy().respond(&discover, PROXYDHCP_PORT).unwrap();
+        assert_eq!(offer.yiaddr, None);
+        assert_eq!(offer.siaddr, Some(TFTP_SERVER));
+
+        let options = offer.get_options();
+        assert_eq!(
+            options[..4],
+            [
+                DHCPOption::DHCPMessageType(DHCPMessageType::DHCPOffer),
+                DHCPOption::ServerIdentifier(SERVER),
+                DHCPOption::ClassIdentifier(b"PXEClient".to_vec()),
+                DHCPOption::ClientMachineIdentifier(UUID),
+            ]
+        );
+        assert!(options.contains(&DHCPOption::BootfileName("ipxe.efi".to_owned())));
+        assert_eq!(
+            pxe_options(&options),
+            Some(vec![PXEOption::DiscoveryControl(PXEDiscoveryControl {
+                disable_multicast: true,
+                use_bootfile: true,
+                ..Default::default()
+            })])
+        );
+
+        // Discovery is only answered on port 67.
+        assert!(proxy().respond(&discover, PXE_BOOT_SERVER_PORT).is_none());
+    }
+
+    #[test]
+    fn boot_server_ack() {
+        let boot_item = VendorInformation::PXE(vec![PXEOption::BootItem(0x8001, 0)]);
+        let uuid = DHCPOption::Option(97, vec![1, 2, 3]);
+        let request = request(
+            DHCPMessageType::DHCPRequest,
+            vec![
+                DHCPOption::ClassIdentifier(PXE_CLIENT.to_vec()),
+                DHCPOption::ClientSystemArchitecture(vec![ClientArchitecture::X86BIOS]),
+                uuid.clone(),
+                boot_item.into(),
+            ],
+        );
+        let ack = proxy().respond(&request, PXE_BOOT_SERVER_PORT).unwrap();
+
+        let options = ack.get_options();
+        assert_eq!(
+            options[0],
+            DHCPOption::DHCPMessageType(DHCPMessageType::DHCPACK)
+        );
+        assert!(options.contains(&uuid));
+        assert!(options.contains(&DHCPOption::BootfileName("undionly.kpxe".to_owned())));
+        assert_eq!(
+            pxe_options(&options),
+            Some(vec![PXEOption::BootItem(0x8001, 0)])
+        );
+    }
+
+    #[test]
+    fn ignored_requests() {
+        let proxy = proxy();
+
+        let discover = request(
+            DHCPMessageType::DHCPDiscover,
+            vec![DHCPOption::ClassIdentifier(b"MSFT 5.0".to_vec())],
+        );
+        assert!(proxy.respond(&discover, PROXYDHCP_PORT).is_none());
+        let discover = request(DHCPMessageType::DHCPDiscover, Vec::new());
+        assert!(proxy.respond(&discover, PROXYDHCP_PORT).is_none());
+
+        // Requests on port 67 are only answered when our offer was selected.
+        let selecting = |server_identifier| {
+            request(
+                DHCPMessageType::DHCPRequest,
+                vec![
+                    DHCPOption::ClassIdentifier(PXE_CLIENT.to_vec()),
+                    DHCPOption::ServerIdentifier(server_identifier),
+                ],
+            )
+        };
+        assert!(proxy
+            .respond(&selecting(Ipv4Addr::new(192, 0, 2, 9)), PROXYDHCP_PORT)
+            .is_none());
+        let ack = proxy.respond(&selecting(SERVER), PROXYDHCP_PORT).unwrap();
+        assert_eq!(
+            ack.get_options()[0],
+            DHCPOption::DHCPMessageType(DHCPMessageType::DHCPACK)
+        );
+    }
+
+    #[test]
+    fn destinations() {
+        let source = SocketAddrV4::new(Ipv4Addr::new(10, 0, 0, 5), 4011);
+        let mut request = request(DHCPMessageType::DHCPDiscover, Vec::new());
+
+        assert_eq!(
+            ProxyDHCP::destination(&request, source, PROXYDHCP_PORT),
+            SocketAddrV4::new(Ipv4Addr::BROADCAST, 68)
+        );
+        request.ciaddr = Some(Ipv4Addr::new(10, 0, 0, 6));
+        assert_eq!(
+            ProxyDHCP::destination(&request, source, PROXYDHCP_PORT),
+            SocketAddrV4::new(Ipv4Addr::new(10, 0, 0, 6), 68)
+        );
+        request.giaddr = Some(Ipv4Addr::new(10, 0, 0, 1));
+        assert_eq!(
+            ProxyDHCP::destination(&request, source, PROXYDHCP_PORT),
+            SocketAddrV4::new(Ipv4Addr::new(10, 0, 0, 1), 67)
+        );
+        assert_eq!(
+            ProxyDHCP::destination(&request, source, PXE_BOOT_SERVER_PORT),
+            source
+        );
+    }
+}
@@ -0,0 +1,435 @@
+//! PXE boot server discovery sub-options carried in option 43.
+//!
+//! Defined by the Preboot Execution Environment (PXE) Specification 2.1 and
+//! sent to clients with the vendor class "PXEClient". Parsing of the vendor
+//! class strings sent by PXE and UEFI HTTP Boot clients is also provided.
+
+use crate::dhcp::{ClientArchitecture, DHCPOption};
+use crate::vendor::VendorInformation;
+use std::net::Ipv4Addr;
+
+/// Enum representing the kind of client in a network boot vendor class.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VendorClassKind {
+    PXEClient,
+    HTTPClient,
+}
+
+/// Struct representing a network boot vendor class identifier (option 60).
+///
+/// The format is `PXEClient:Arch:xxxxx:UNDI:yyyzzz` for PXE clients and
+/// `HTTPClient:Arch:xxxxx:UNDI:yyyzzz` for UEFI HTTP Boot clients, where
+/// `xxxxx` is the decimal client architecture and `yyy`/`zzz` the decimal UNDI
+/// major and minor version. Servers echo just the kind back to the client.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BootVendorClass {
+    pub kind: VendorClassKind,
+    pub architecture: Option<ClientArchitecture>,
+    pub undi: Option<(u8, u8)>,
+}
+
+impl BootVendorClass {
+    /// Parse a network boot vendor class, returning `None` if it is malformed.
+    pub fn from_bytes(bytes: &[u8]) -> Option<BootVendorClass> {
+        let class = std::str::from_utf8(bytes).ok()?;
+        let mut fields = class.split(':');
+
+        let kind = match fields.next()? {
+            "PXEClient" => VendorClassKind::PXEClient,
+            "HTTPClient" => VendorClassKind::HTTPClient,
+            _ => return None,
+        };
+
+        let mut architecture: Option<ClientArchitecture> = None;
+        let mut undi: Option<(u8, u8)> = None;
+        while let Some(field) = fields.next() {
+            let value = fields.next()?;
+            match field {
+                "Arch" if value.len() == 5 => {
+                    architecture = Some(value.parse::<u16>().ok()?.into());
+                }
+                "UNDI" if value.len() == 6 && value.is_ascii() => {
+                    undi = Some((value[..3].parse().ok()?, value[3..].parse().ok()?));
+                }
+                _ => return None,
+            }
+        }
+
+        Some(BootVendorClass {
+            kind,
+            architecture,
+            undi,
+        })
+    }
+
+    /// Parse the vendor class identifier from a list of options.
+    pub fn from_options(options: &[DHCPOption]) -> Option<BootVendorClass> {
+        options.iter().find_map(|option| match option {
+            DHCPOption::ClassIdentifier(b) => BootVendorClass::from_bytes(b),
+            _ => None,
+        })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut class = match self.kind {
+            VendorClassKind::PXEClient => String::from("PXEClient"),
+            VendorClassKind::HTTPClient => String::from("HTTPClient"),
+        };
+
+        if let Some(architecture) = self.architecture {
+            class += &format!(":Arch:{:05}", u16::from(architecture));
+        }
+        if let Some((major, minor)) = self.undi {
+            class += &format!(":UNDI:{:03}{:03}", major, minor);
+        }
+
+        class.into_bytes()
+    }
+}
+
+/// Struct representing the parameter for PXE sub-option 6 "Discovery Control".
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PXEDiscoveryControl {
+    pub disable_broadcast: bool, // Bit 0
+    pub disable_multicast: bool, // Bit 1
+    pub boot_servers_only: bool, // Bit 2, only use/accept servers listed in PXE_BOOT_SERVERS
+    pub use_bootfile: bool,      // Bit 3, download the boot file without prompting or discovery
+}
+
+impl From<u8> for PXEDiscoveryControl {
+    fn from(item: u8) -> Self {
+        Self {
+            disable_broadcast: item & 1 != 0,
+            disable_multicast: item & 2 != 0,
+            boot_servers_only: item & 4 != 0,
+            use_bootfile: item & 8 != 0,
+        }
+    }
+}
+
+impl From<PXEDiscoveryControl> for u8 {
+    fn from(item: PXEDiscoveryControl) -> Self {
+        item.disable_broadcast as u8
+            | (item.disable_multicast as u8) << 1
+            | (item.boot_servers_only as u8) << 2
+            | (item.use_bootfile as u8) << 3
+    }
+}
+
+/// An entry of PXE sub-option 8 "Boot Servers".
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PXEBootServer {
+    pub server_type: u16,
+    pub addresses: Vec<Ipv4Addr>,
+}
+
+/// An entry of PXE sub-option 9 "Boot Menu".
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PXEBootMenuItem {
+    pub server_type: u16,
+    pub description: String,
+}
+
+/// This enum represents the PXE sub-options of option 43 supported by dhcprs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PXEOption {
+    DiscoveryControl(PXEDiscoveryControl), // 6 1 n
+    BootServers(Vec<PXEBootServer>),       // 8 n t1 t2 c a1 a2 a3 a4 ... t1 t2 c ...
+    BootMenu(Vec<PXEBootMenuItem>),        // 9 n t1 t2 l d1 d2 ... t1 t2 l ...
+    MenuPrompt(u8, String), // 10 n t p1 p2 ... (t = 0 -> boot first item, t = 255 -> wait for selection)
+    BootItem(u16, u16),     // 71 4 t1 t2 l1 l2
+
+    // Catchall
+    Option(u8, Vec<u8>),
+}
+
+fn decode_boot_servers(mut bytes: &[u8]) -> Option<Vec<PXEBootServer>> {
+    let mut servers: Vec<PXEBootServer> = Vec::new();
+
+    while !bytes.is_empty() {
+        let server_type = u16::from_be_bytes([*bytes.first()?, *bytes.get(1)?]);
+        let count = *bytes.get(2)? as usize;
+        let addresses = bytes
+            .get(3..3 + count * 4)?
+            .chunks(4)
+            .map(|c| Ipv4Addr::new(c[0], c[1], c[2], c[3]))
+            .collect();
+
+        servers.push(PXEBootServer {
+            server_type,
+            addresses,
+        });
+        bytes = &bytes[3 + count * 4..];
+    }
+
+    Some(servers)
+}
+
+fn decode_boot_menu(mut bytes: &[u8]) -> Option<Vec<PXEBootMenuItem>> {
+    let mut items: Vec<PXEBootMenuItem> = Vec::new();
+
+    while !bytes.is_empty() {
+        let server_type = u16::from_be_bytes([*bytes.first()?, *bytes.get(1)?]);
+        let length = *bytes.get(2)? as usize;
+        let description = std::str::from_utf8(bytes.get(3..3 + length)?).ok()?;
+
+        items.push(PXEBootMenuItem {
+            server_type,
+            description: description.to_owned(),
+        });
+        bytes = &bytes[3 + length..];
+    }
+
+    Some(items)
+}
+
+impl PXEOption {
+    pub fn from_bytes(bytes: &[u8]) -> Option<Vec<PXEOption>> {
+        let options = crate::vendor::decode_encapsulated(bytes)?
+            .into_iter()
+            .map(|(code, data)| {
+                let option = match code {
+                    6 if data.len() == 1 => Some(PXEOption::DiscoveryControl(data[0].into())),
+                    8 => decode_boot_servers(&data).map(PXEOption::BootServers),
+                    9 => decode_boot_menu(&data).map(PXEOption::BootMenu),
+                    10 if !data.is_empty() => std::str::from_utf8(&data[1..])
+                        .ok()
+                        .map(|s| PXEOption::MenuPrompt(data[0], s.to_owned())),
+                    71 if data.len() == 4 => Some(PXEOption::BootItem(
+                        u16::from_be_bytes([data[0], data[1]]),
+                        u16::from_be_bytes([data[2], data[3]]),
+                    )),
+                    _ => None,
+                };
+
+                // Keep sub-options we cannot decode in the catchall rather than dropping them.
+                option.unwrap_or(PXEOption::Option(code, data))
+            })
+            .collect();
+
+        Some(options)
+    }
+
+    /// Encodes the sub-options, terminated by an End sub-option as required by PXE.
+    ///
+    /// Sub-options too long to encode are skipped.
+    pub fn to_bytes(options: &[PXEOption]) -> Vec<u8> {
+        let mut bytes: Vec<u8> = Vec::new();
+
+        for option in options {
+            match option {
+                PXEOption::DiscoveryControl(c) => {
+                    bytes.push(6);
+                    bytes.push(1);
+                    bytes.push((*c).into());
+                }
+
+                PXEOption::BootServers(servers) => {
+                    let length = servers
+                        .iter()
+                        .fold(0, |acc, s| acc + 3 + s.addresses.len() * 4);
+                    if length > 255 {
+                        continue;
+                    }
+
+                    bytes.push(8);
+                    bytes.push(length as u8);
+                    for server in servers {
+                        bytes.extend_from_slice(&server.server_type.to_be_bytes());
+                        bytes.push(server.addresses.len() as u8);
+                        for addr in &server.addresses {
+                            bytes.extend_from_slice(&addr.octets());
+                        }
+                    }
+                }
+
+                PXEOption::BootMenu(items) => {
+                    let length = items.iter().fold(0, |acc, i| acc + 3 + i.description.len());
+                    if length > 255 {
+                        continue;
+                    }
+
+                    bytes.push(9);
+                    bytes.push(length as u8);
+                    for item in items {
+                        bytes.extend_from_slice(&item.server_type.to_be_bytes());
+                        bytes.push(item.description.len() as u8);
+                        bytes.extend_from_slice(item.description.as_bytes());
+                    }
+                }
+
+                PXEOption::MenuPrompt(timeout, prompt) => {
+                    if prompt.len() + 1 > 255 {
+                        continue;
+                    }
+
+                    bytes.push(10);
+                    bytes.push((prompt.len() + 1) as u8);
+                    bytes.push(*timeout);
+                    bytes.extend_from_slice(prompt.as_bytes());
+                }
+
+                PXEOption::BootItem(server_type, layer) => {
+                    bytes.push(71);
+                    bytes.push(4);
+                    bytes.extend_from_slice(&server_type.to_be_bytes());
+                    bytes.extend_from_slice(&layer.to_be_bytes());
+                }
+
+                PXEOption::Option(n, b) => {
+                    if b.len() > 255 {
+                        continue;
+                    }
+
+                    bytes.push(*n);
+                    bytes.push(b.len() as u8);
+                    bytes.extend_from_slice(b);
+                }
+            }
+        }
+
+        bytes.push(255);
+        bytes
+    }
+}
+
+/// Decoder for vendor classes starting with "PXEClient".
+pub fn decode_pxe(bytes: &[u8]) -> Option<VendorInformation> {
+    Some(VendorInformation::PXE(PXEOption::from_bytes(bytes)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vendor_class() {
+        let class = BootVendorClass::from_bytes(b"HTTPClient:Arch:00016:UNDI:003001").unwrap();
+        assert_eq!(
+            class,
+            BootVendorClass {
+                kind: VendorClassKind::HTTPClient,
+                architecture: Some(ClientArchitecture::EFIX86_64HTTP),
+                undi: Some((3, 1)),
+            }
+        );
+        assert_eq!(class.to_bytes(), b"HTTPClient:Arch:00016:UNDI:003001");
+
+        assert_eq!(
+            BootVendorClass::from_bytes(b"PXEClient"),
+            Some(BootVendorClass {
+                kind: VendorClassKind::PXEClient,
+                architecture: None,
+                undi: None,
+            })
+        );
+    }
+
+    #[test]
+    fn malformed_vendor_class() {
+        let malformed: [&[u8]; 7] = [
+            b"HTTPClient:Arch:00016:UNDI",
+            b"PXEClient:Arch",
+            b"PXEClient:Arch:16",
+            b"PXEClient:Arch:00007:UNDI:3001",
+            b"PXEClient:Arch:00007:Other:1",
+            b"PXEClientX",
+            b"MSFT 5.0",
+        ];
+
+        for class in malformed {
+            assert_eq!(BootVendorClass::from_bytes(class), None);
+        }
+    }
+
+    #[test]
+    fn suboptions() {
+        let bytes = [
+            6, 1, 0x0B, 8, 7, 0, 1, 1, 192, 0, 2, 1, 9, 8, 0, 0, 5, b'l', b'o', b'c', b'a', b'l',
+            10, 5, 3, b'B', b'o', b'o', b't', 71, 4, 0x80, 0x01, 0, 0, 42, 1, 7, 255,
+        ];
+        let options = vec![
+            PXEOption::DiscoveryControl(PXEDiscoveryControl {
+                disable_broadcast: true,
+                disable_multicast: true,
+                boot_servers_only: false,
+                use_bootfile: true,
+            }),
+            PXEOption::BootServers(vec![PXEBootServer {
+                server_type: 1,
+                addresses: vec![Ipv4Addr::new(192, 0, 2, 1)],
+            }]),
+            PXEOption::BootMenu(vec![PXEBootMenuItem {
+                server_type: 0,
+                description: "local".to_owned(),
+            }]),
+            PXEOption::MenuPrompt(3, "Boot".to_owned()),
+            PXEOption::BootItem(0x8001, 0),
+            PXEOption::Option(42, vec![7]),
+        ];
+
+        assert_eq!(PXEOption::from_bytes(&bytes), Some(options.clone()));
+        assert_eq!(PXEOption::to_bytes(&options), bytes);
+
+        // The End sub-option is always present and ends decoding.
+        assert_eq!(PXEOption::to_bytes(&[]), [255]);
+        assert_eq!(
+            PXEOption::from_bytes(&[71, 4, 0, 1, 0, 0, 255, 6, 1, 0]),
+            Some(vec![PXEOption::BootItem(1, 0)])
+        );
+
+        // Sub-options which cannot be decoded are kept raw.
+        assert_eq!(
+            PXEOption::from_bytes(&[8, 4, 0, 1, 1, 192, 71, 2, 0, 1, 255]),
+            Some(vec![
+                PXEOption::Option(8, vec![0, 1, 1, 192]),
+                PXEOption::Option(71, vec![0, 1]),
+            ])
+        );
+        assert_eq!(
+            PXEOption::from_bytes(&[9, 4, 0, 0, 5, b'l']),
+            Some(vec![PXEOption::Option(9, vec![0, 0, 5, b'l'])])
+        );
+
+        // Truncated sub-options are rejected.
+        assert_eq!(PXEOption::from_bytes(&[9, 6, 0, 0, 5, b'l']), None);
+    }
+
+    #[test]
+    fn overlong_suboptions() {
+        let server = PXEBootServer {
+            server_type: 1,
+            addresses: vec![Ipv4Addr::new(192, 0, 2, 1); 63],
+        };
+        let item = PXEBootMenuItem {
+            server_type: 0,
+            description: "a".repeat(252),
+        };
+
+        // The longest sub-options still fit.
+        let longest = vec![
+            PXEOption::BootServers(vec![server.clone()]),
+            PXEOption::BootMenu(vec![item.clone()]),
+            PXEOption::MenuPrompt(0, "a".repeat(254)),
+        ];
+        assert_eq!(
+            PXEOption::from_bytes(&PXEOption::to_bytes(&longest)),
+            Some(longest)
+        );
+
+        let mut servers = vec![server.clone(), server];
+        servers[1].addresses.truncate(1);
+        let overlong = [
+            PXEOption::BootServers(servers),
+            PXEOption::BootMenu(vec![item.clone(), item]),
+            PXEOption::BootMenu(vec![PXEBootMenuItem {
+                server_type: 0,
+                description: "a".repeat(253),
+            }]),
+            PXEOption::MenuPrompt(0, "a".repeat(255)),
+            PXEOption::Option(42, vec![0; 256]),
+            PXEOption::BootItem(1, 0),
+        ];
+        assert_eq!(PXEOption::to_bytes(&overlong), [71, 4, 0, 1, 0, 0, 255]);
+    }
+}
@@ -0,0 +1,265 @@
+//! DHCP server side helpers.
+//!
+//! These perform no IO and hold no lease state, they are intended to be used
+//! by servers built on top of `BOOTPPacket` and `DHCPOption`.
+
+use crate::bootp::{BOOTPPacket, OpCode};
+use crate::dhcp::{DHCPMessageType, DHCPOption};
+use std::net::Ipv4Addr;
+
+/// Where the link a request belongs to was determined from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkSource {
+    /// The link selection sub-option (5) of the relay agent information option (82), RFC3527.
+    RelayLinkSelection,
+    /// The subnet selection option (118), RFC3011.
+    SubnetSelection,
+    /// The relay agent address in the BOOTP header.
+    RelayAgent,
+    /// The address of the interface the request was received on.
+    Interface,
+}
+
+// Link selection sub-option of option 82, this crate does not decode option
+// 82 so it is read from the catchall.
+fn relay_link_selection(options: &[DHCPOption]) -> Option<Ipv4Addr> {
+    options.iter().find_map(|o| match o {
+        DHCPOption::Option(82, b) => {
+            crate::vendor::decode_encapsulated(b)?
+                .into_iter()
+                .find_map(|(code, data)| match (code, &data[..]) {
+                    (5, [a1, a2, a3, a4]) => Some(Ipv4Addr::new(*a1, *a2, *a3, *a4)),
+                    _ => None,
+                })
+        }
+        _ => None,
+    })
+}
+
+/// Determine the link a request belongs to, and so the subnet an address should be assigned from.
+///
+/// The precedence is the link selection sub-option of option 82, the subnet
+/// selection option, the relay agent address and finally
+/// `interface_address`, the address of the interface the request was
+/// received on. Unspecified (0.0.0.0) addresses are ignored.
+pub fn select_link(request: &BOOTPPacket, interface_address: Ipv4Addr) -> (Ipv4Addr, LinkSource) {
+    let options = request.get_options();
+
+    let subnet_selection = options.iter().find_map(|o| match o {
+        DHCPOption::SubnetSelection(addr) => Some(*addr),
+        _ => None,
+    });
+
+    [
+        (
+            relay_link_selection(&options),
+            LinkSource::RelayLinkSelection,
+        ),
+        (subnet_selection, LinkSource::SubnetSelection),
+        (request.giaddr, LinkSource::RelayAgent),
+    ]
+    .into_iter()
+    .find_map(|(addr, source)| Some((addr.filter(|a| !a.is_unspecified())?, source)))
+    .unwrap_or((interface_address, LinkSource::Interface))
+}
+
+fn is_discover(options: &[DHCPOption]) -> bool {
+    options.iter().any(|o| {
+        matches!(
+            o,
+            DHCPOption::DHCPMessageType(DHCPMessageType::DHCPDiscover)
+        )
+    })
+}
+
+/// Whether `request` is a DHCPDISCOVER asking for the two message exchange with option 80.
+pub fn wants_rapid_commit(request: &BOOTPPacket) -> bool {
+    let options = request.get_options();
+    is_discover(&options) && options.iter().any(|o| matches!(o, DHCPOption::RapidCommit))
+}
+
+/// Build the reply to a DHCPDISCOVER, offering `yiaddr`.
+///
+/// `options` should contain the options for the reply, including the server
+/// identifier and lease time, the message type is added to them. If
+/// `rapid_commit` is true and the client asked for it the reply is a
+/// DHCPACK carrying option 80 and the server must commit the lease before
+/// sending it, otherwise it is a DHCPOFFER. Returns `None` if the request is
+/// not a DHCPDISCOVER or the options do not fit.
+pub fn discover_reply(
+    request: &BOOTPPacket,
+    yiaddr: Ipv4Addr,
+    options: Vec<DHCPOption>,
+    rapid_commit: bool,
+) -> Option<BOOTPPacket> {
+    if !matches!(request.op, OpCode::BOOTREQUEST) || !is_discover(&request.get_options()) {
+        return None;
+    }
+
+    let commit = rapid_commit && wants_rapid_commit(request);
+    let message_type = if commit {
+        DHCPMessageType::DHCPACK
+    } else {
+        DHCPMessageType::DHCPOffer
+    };
+
+    let mut reply_options = vec![DHCPOption::DHCPMessageType(message_type)];
+    reply_options.extend(options.into_iter().filter(|o| {
+        !matches!(
+            o,
+            DHCPOption::End | DHCPOption::DHCPMessageType(_) | DHCPOption::RapidCommit
+        )
+    }));
+    if commit {
+        reply_options.push(DHCPOption::RapidCommit);
+    }
+    reply_options.push(DHCPOption::End);
+
+    let mut reply = request.reply();
+    reply.yiaddr = Some(yiaddr);
+    reply.set_options(reply_options).ok()?;
+
+    Some(reply)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use eui48::MacAddress;
+
+    fn request(giaddr: Option<Ipv4Addr>, mut options: Vec<DHCPOption>) -> BOOTPPacket {
+        let mut request = BOOTPPacket::new(
+            OpCode::BOOTREQUEST,
+            0,
+            1,
+            0,
+            0,
+            None,
+            None,
+            None,
+            giaddr,
+            MacAddress::new([2, 0, 0, 0, 0, 1]),
+            [0; 64],
+            [0; 128],
+            [0; 312],
+        );
+        options.push(DHCPOption::End);
+        request.set_options(options).unwrap();
+        request
+    }
+
+    const INTERFACE: Ipv4Addr = Ipv4Addr::new(10, 0, 0, 1);
+    const RELAY: Ipv4Addr = Ipv4Addr::new(10, 1, 0, 1);
+    const SUBNET: Ipv4Addr = Ipv4Addr::new(10, 2, 0, 0);
+    const LINK: Ipv4Addr = Ipv4Addr::new(10, 3, 0, 0);
+
+    fn relay_agent_information(link: Ipv4Addr) -> DHCPOption {
+        let mut bytes = vec![1, 2, b'e', b'0', 5, 4];
+        bytes.extend_from_slice(&link.octets());
+        DHCPOption::Option(82, bytes)
+    }
+
+    #[test]
+    fn link_selection_order() {
+        let all = vec![
+            DHCPOption::SubnetSelection(SUBNET),
+            relay_agent_information(LINK),
+        ];
+        assert_eq!(
+            select_link(&request(Some(RELAY), all), INTERFACE),
+            (LINK, LinkSource::RelayLinkSelection)
+        );
+
+        let subnet = vec![DHCPOption::SubnetSelection(SUBNET)];
+        assert_eq!(
+            select_link(&request(Some(RELAY), subnet), INTERFACE),
+            (SUBNET, LinkSource::SubnetSelection)
+        );
+
+        assert_eq!(
+            select_link(&request(Some(RELAY), Vec::new()), INTERFACE),
+            (RELAY, LinkSource::RelayAgent)
+        );
+        assert_eq!(
+            select_link(&request(None, Vec::new()), INTERFACE),
+            (INTERFACE, LinkSource::Interface)
+        );
+    }
+
+    #[test]
+    fn unspecified_addresses_are_ignored() {
+        let options = vec![
+            DHCPOption::SubnetSelection(Ipv4Addr::UNSPECIFIED),
+            relay_agent_information(Ipv4Addr::UNSPECIFIED),
+        ];
+        assert_eq!(
+            select_link(&request(Some(RELAY), options), INTERFACE),
+            (RELAY, LinkSource::RelayAgent)
+        );
+
+        // Option 82 without the link selection sub-option.
+        let options = vec![DHCPOption::Option(82, vec![1, 2, b'e', b'0'])];
+        assert_eq!(
+            select_link(&request(None, options), INTERFACE),
+            (INTERFACE, LinkSource::Interface)
+        );
+    }
+
+    fn discover(rapid_commit: bool) -> BOOTPPacket {
+        let mut options = vec![DHCPOption::DHCPMessageType(DHCPMessageType::DHCPDiscover)];
+        if rapid_commit {
+            options.push(DHCPOption::RapidCommit);
+        }
+        request(None, options)
+    }
+
+    fn reply_options() -> Vec<DHCPOption> {
+        vec![
+            DHCPOption::ServerIdentifier(INTERFACE),
+            DHCPOption::IPAddressLeaseTime(3600),
+            DHCPOption::RapidCommit,
+            DHCPOption::End,
+        ]
+    }
+
+    #[test]
+    fn rapid_commit() {
+        let address = Ipv4Addr::new(10, 0, 0, 10);
+        assert!(wants_rapid_commit(&discover(true)));
+        assert!(!wants_rapid_commit(&discover(false)));
+
+        let reply = discover_reply(&discover(true), address, reply_options(), true).unwrap();
+        assert_eq!(reply.yiaddr, Some(address));
+        assert_eq!(
+            reply.get_options(),
+            [
+                DHCPOption::DHCPMessageType(DHCPMessageType::DHCPACK),
+                DHCPOption::ServerIdentifier(INTERFACE),
+                DHCPOption::IPAddressLeaseTime(3600),
+                DHCPOption::RapidCommit,
+            ]
+        );
+
+        // Without option 80 from the client, or with rapid commit disabled, the reply is an offer.
+        let offer = [
+            DHCPOption::DHCPMessageType(DHCPMessageType::DHCPOffer),
+            DHCPOption::ServerIdentifier(INTERFACE),
+            DHCPOption::IPAddressLeaseTime(3600),
+        ];
+        let reply = discover_reply(&discover(false), address, reply_options(), true).unwrap();
+        assert_eq!(reply.get_options(), offer);
+        let reply = discover_reply(&discover(true), address, reply_options(), false).unwrap();
+        assert_eq!(reply.get_options(), offer);
+    }
+
+    #[test]
+    fn rapid_commit_requires_discover() {
+        let options = vec![
+            DHCPOption::DHCPMessageType(DHCPMessageType::DHCPRequest),
+            DHCPOption::RapidCommit,
+        ];
+        let request = request(None, options);
+        assert!(!wants_rapid_commit(&request));
+        assert!(discover_reply(&request, INTERFACE, reply_options(), true).is_none());
+    }
+}
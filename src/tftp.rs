@@ -0,0 +1,760 @@
+//! Minimal read-only TFTP server for network booting.
+//!
+//! Implements RFC1350 with option negotiation (RFC2347) of the blksize
+//! (RFC2348), tsize and timeout (RFC2349) and windowsize (RFC7440) options.
+//!
+//! `TFTPTransfer` drives a single transfer without performing any network IO,
+//! `TFTPServer::run` is provided to serve files from a root directory over a
+//! `std::net::UdpSocket`.
+
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::net::{SocketAddr, UdpSocket};
+use std::path::{Component, Path, PathBuf};
+use std::time::Duration;
+
+/// UDP port TFTP requests are received on.
+pub const TFTP_PORT: u16 = 69;
+
+/// Enum representing the error codes of a TFTP ERROR packet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TFTPErrorCode {
+    // RFC1350
+    NotDefined,
+    FileNotFound,
+    AccessViolation,
+    DiskFull,
+    IllegalOperation,
+    UnknownTransferID,
+    FileExists,
+    NoSuchUser,
+
+    // RFC2347
+    OptionNegotiation,
+}
+
+impl From<u16> for TFTPErrorCode {
+    fn from(item: u16) -> Self {
+        match item {
+            1 => TFTPErrorCode::FileNotFound,
+            2 => TFTPErrorCode::AccessViolation,
+            3 => TFTPErrorCode::DiskFull,
+            4 => TFTPErrorCode::IllegalOperation,
+            5 => TFTPErrorCode::UnknownTransferID,
+            6 => TFTPErrorCode::FileExists,
+            7 => TFTPErrorCode::NoSuchUser,
+            8 => TFTPErrorCode::OptionNegotiation,
+            _ => TFTPErrorCode::NotDefined,
+        }
+    }
+}
+
+impl From<TFTPErrorCode> for u16 {
+    fn from(item: TFTPErrorCode) -> Self {
+        match item {
+            TFTPErrorCode::NotDefined => 0,
+            TFTPErrorCode::FileNotFound => 1,
+            TFTPErrorCode::AccessViolation => 2,
+            TFTPErrorCode::DiskFull => 3,
+            TFTPErrorCode::IllegalOperation => 4,
+            TFTPErrorCode::UnknownTransferID => 5,
+            TFTPErrorCode::FileExists => 6,
+            TFTPErrorCode::NoSuchUser => 7,
+            TFTPErrorCode::OptionNegotiation => 8,
+        }
+    }
+}
+
+/// This enum represents the TFTP packets supported by dhcprs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TFTPPacket {
+    ReadRequest(String, String, Vec<(String, String)>), // 1 filename 0 mode 0 option 0 value 0 ...
+    WriteRequest(String, String, Vec<(String, String)>), // 2 filename 0 mode 0 option 0 value 0 ...
+    Data(u16, Vec<u8>),                                 // 3 b1 b2 d1 d2 ...
+    Acknowledgement(u16),                               // 4 b1 b2
+    Error(TFTPErrorCode, String),                       // 5 e1 e2 message 0
+    OptionAcknowledgement(Vec<(String, String)>),       // 6 option 0 value 0 ...
+}
+
+fn decode_strings(bytes: &[u8]) -> Option<Vec<String>> {
+    let (last, strings) = bytes.split_last()?;
+    if *last != 0 {
+        return None;
+    }
+
+    strings
+        .split(|b| *b == 0)
+        .map(|s| std::str::from_utf8(s).ok().map(|s| s.to_owned()))
+        .collect()
+}
+
+fn decode_options(mut strings: std::vec::IntoIter<String>) -> Vec<(String, String)> {
+    let mut options: Vec<(String, String)> = Vec::new();
+    while let (Some(option), Some(value)) = (strings.next(), strings.next()) {
+        options.push((option, value));
+    }
+    options
+}
+
+fn encode_strings(bytes: &mut Vec<u8>, strings: &[&str]) {
+    for s in strings {
+        bytes.extend_from_slice(s.as_bytes());
+        bytes.push(0);
+    }
+}
+
+impl TFTPPacket {
+    pub fn from_bytes(bytes: &[u8]) -> Option<TFTPPacket> {
+        let opcode = u16::from_be_bytes([*bytes.first()?, *bytes.get(1)?]);
+        let bytes = &bytes[2..];
+
+        match opcode {
+            1 | 2 => {
+                let mut strings = decode_strings(bytes)?.into_iter();
+                let (filename, mode) = (strings.next()?, strings.next()?);
+                let options = decode_options(strings);
+
+                if opcode == 1 {
+                    Some(TFTPPacket::ReadRequest(filename, mode, options))
+                } else {
+                    Some(TFTPPacket::WriteRequest(filename, mode, options))
+                }
+            }
+
+            3 => {
+                let block = u16::from_be_bytes([*bytes.first()?, *bytes.get(1)?]);
+                Some(TFTPPacket::Data(block, bytes[2..].to_vec()))
+            }
+
+            4 => {
+                let block = u16::from_be_bytes([*bytes.first()?, *bytes.get(1)?]);
+                Some(TFTPPacket::Acknowledgement(block))
+            }
+
+            5 => {
+                let code = u16::from_be_bytes([*bytes.first()?, *bytes.get(1)?]);
+                // Be lenient with a missing terminator on the message.
+                let message = bytes[2..].split(|b| *b == 0).next().unwrap_or_default();
+                Some(TFTPPacket::Error(
+                    code.into(),
+                    String::from_utf8_lossy(message).into_owned(),
+                ))
+            }
+
+            6 => {
+                let strings = decode_strings(bytes)?.into_iter();
+                Some(TFTPPacket::OptionAcknowledgement(decode_options(strings)))
+            }
+
+            _ => None,
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes: Vec<u8> = Vec::new();
+
+        match self {
+            TFTPPacket::ReadRequest(filename, mode, options)
+            | TFTPPacket::WriteRequest(filename, mode, options) => {
+                let opcode: u16 = if let TFTPPacket::ReadRequest(..) = self {
+                    1
+                } else {
+                    2
+                };
+                bytes.extend_from_slice(&opcode.to_be_bytes());
+                encode_strings(&mut bytes, &[filename, mode]);
+                for (option, value) in options {
+                    encode_strings(&mut bytes, &[option, value]);
+                }
+            }
+
+            TFTPPacket::Data(block, data) => {
+                bytes.extend_from_slice(&3_u16.to_be_bytes());
+                bytes.extend_from_slice(&block.to_be_bytes());
+                bytes.extend_from_slice(data);
+            }
+
+            TFTPPacket::Acknowledgement(block) => {
+                bytes.extend_from_slice(&4_u16.to_be_bytes());
+                bytes.extend_from_slice(&block.to_be_bytes());
+            }
+
+            TFTPPacket::Error(code, message) => {
+                bytes.extend_from_slice(&5_u16.to_be_bytes());
+                bytes.extend_from_slice(&u16::from(*code).to_be_bytes());
+                encode_strings(&mut bytes, &[message]);
+            }
+
+            TFTPPacket::OptionAcknowledgement(options) => {
+                bytes.extend_from_slice(&6_u16.to_be_bytes());
+                for (option, value) in options {
+                    encode_strings(&mut bytes, &[option, value]);
+                }
+            }
+        }
+
+        bytes
+    }
+}
+
+/// Options in effect for a transfer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TFTPOptions {
+    pub block_size: u16,  // RFC2348, 8 to 65464 octets
+    pub timeout: u8,      // RFC2349, 1 to 255 seconds
+    pub window_size: u16, // RFC7440, 1 to 65535 blocks
+}
+
+impl Default for TFTPOptions {
+    fn default() -> Self {
+        Self {
+            block_size: 512,
+            timeout: 5,
+            window_size: 1,
+        }
+    }
+}
+
+impl TFTPOptions {
+    /// Negotiate the options requested by a client.
+    ///
+    /// Returns the options in effect and the options to acknowledge in an
+    /// OACK, which is empty if no options were accepted. Unknown or invalid
+    /// options are ignored as required by RFC2347.
+    pub fn negotiate(requested: &[(String, String)], size: u64) -> (Self, Vec<(String, String)>) {
+        let mut options = Self::default();
+        let mut acknowledged: Vec<(String, String)> = Vec::new();
+
+        for (option, value) in requested {
+            let option = option.to_ascii_lowercase();
+            let value: u64 = match value.parse() {
+                Ok(v) => v,
+                Err(_) => continue,
+            };
+
+            match option.as_str() {
+                "blksize" if value >= 8 => {
+                    options.block_size = value.min(65464) as u16;
+                    acknowledged.push((option, options.block_size.to_string()));
+                }
+                "timeout" if (1..=255).contains(&value) => {
+                    options.timeout = value as u8;
+                    acknowledged.push((option, value.to_string()));
+                }
+                "tsize" => acknowledged.push((option, size.to_string())),
+                "windowsize" if value >= 1 => {
+                    options.window_size = value.min(65535) as u16;
+                    acknowledged.push((option, options.window_size.to_string()));
+                }
+                _ => (),
+            }
+        }
+
+        (options, acknowledged)
+    }
+}
+
+/// State of a single read transfer.
+///
+/// Blocks are numbered from 1 internally without wrapping, only the block
+/// number put on the wire wraps around after 65535 so that files larger than
+/// 65535 blocks can be transferred.
+pub struct TFTPTransfer<R> {
+    file: R,
+    options: TFTPOptions,
+    oack: Vec<(String, String)>,
+    blocks: u64,
+    acknowledged: u64,
+    oack_acknowledged: bool,
+}
+
+impl<R: Read + Seek> TFTPTransfer<R> {
+    /// Create a transfer of `size` bytes read from `file`, negotiating the
+    /// options from the client's read request.
+    pub fn new(file: R, size: u64, requested: &[(String, String)]) -> Self {
+        let (options, oack) = TFTPOptions::negotiate(requested, size);
+        let oack_acknowledged = oack.is_empty();
+
+        Self {
+            file,
+            options,
+            oack,
+            // The final block is always shorter than the block size, even if empty.
+            blocks: size / options.block_size as u64 + 1,
+            acknowledged: 0,
+            oack_acknowledged,
+        }
+    }
+
+    /// The options in effect for this transfer.
+    pub fn options(&self) -> TFTPOptions {
+        self.options
+    }
+
+    /// Whether the client has acknowledged every block.
+    pub fn is_finished(&self) -> bool {
+        self.acknowledged == self.blocks
+    }
+
+    /// Packets to send to begin the transfer or to retransmit after a timeout.
+    pub fn transmit(&mut self) -> std::io::Result<Vec<TFTPPacket>> {
+        if !self.oack_acknowledged {
+            return Ok(vec![TFTPPacket::OptionAcknowledgement(self.oack.clone())]);
+        }
+
+        let mut packets: Vec<TFTPPacket> = Vec::new();
+        let last = self
+            .blocks
+            .min(self.acknowledged + self.options.window_size as u64);
+        let block_size = self.options.block_size as u64;
+
+        self.file
+            .seek(SeekFrom::Start(self.acknowledged * block_size))?;
+        for block in self.acknowledged + 1..=last {
+            let mut data: Vec<u8> = Vec::new();
+            (&mut self.file).take(block_size).read_to_end(&mut data)?;
+            packets.push(TFTPPacket::Data(block as u16, data));
+        }
+
+        Ok(packets)
+    }
+
+    /// Handle an acknowledgement from the client, returning the packets to send next.
+    ///
+    /// Acknowledgements which do not advance the transfer are ignored to
+    /// avoid the Sorcerer's Apprentice Syndrome, unless a window size above
+    /// one is in use where they signal that the window must be resent.
+    pub fn acknowledge(&mut self, block: u16) -> std::io::Result<Vec<TFTPPacket>> {
+        if !self.oack_acknowledged {
+            if block != 0 {
+                return Ok(Vec::new());
+            }
+            self.oack_acknowledged = true;
+            return self.transmit();
+        }
+
+        let window = self.options.window_size as u64;
+        let acknowledged = (0..=window)
+            .map(|n| self.acknowledged + n)
+            .find(|n| *n <= self.blocks && *n as u16 == block);
+
+        match acknowledged {
+            Some(n) if n == self.acknowledged && window == 1 => Ok(Vec::new()),
+            Some(n) => {
+                self.acknowledged = n;
+                if self.is_finished() {
+                    return Ok(Vec::new());
+                }
+                self.transmit()
+            }
+            None => Ok(Vec::new()),
+        }
+    }
+}
+
+/// Read-only TFTP server serving files below a root directory.
+#[derive(Clone)]
+pub struct TFTPServer {
+    root: PathBuf,
+    retries: u32,
+}
+
+impl TFTPServer {
+    /// Create a server serving files from `root`.
+    pub fn new<P: AsRef<Path>>(root: P) -> std::io::Result<Self> {
+        Ok(Self {
+            root: root.as_ref().canonicalize()?,
+            retries: 5,
+        })
+    }
+
+    /// Resolve a requested file name to a path below the root directory.
+    ///
+    /// Leading slashes are ignored and backslashes are treated as separators,
+    /// as sent by some PXE clients. Requests containing `..` components are
+    /// refused without being normalised, even ones such as `sub/../sub/a`
+    /// which would stay below the root. Requests resolving outside of the
+    /// root, for example through a symbolic link, are refused too.
+    pub fn resolve(&self, filename: &str) -> Result<PathBuf, TFTPErrorCode> {
+        let filename = filename.replace('\\', "/");
+        let relative = Path::new(filename.trim_start_matches('/'));
+
+        if relative
+            .components()
+            .any(|c| !matches!(c, Component::Normal(_) | Component::CurDir))
+        {
+            return Err(TFTPErrorCode::AccessViolation);
+        }
+
+        let path = self
+            .root
+            .join(relative)
+            .canonicalize()
+            .map_err(|_| TFTPErrorCode::FileNotFound)?;
+
+        if !path.starts_with(&self.root) {
+            return Err(TFTPErrorCode::AccessViolation);
+        }
+        if !path.is_file() {
+            return Err(TFTPErrorCode::FileNotFound);
+        }
+
+        Ok(path)
+    }
+
+    /// Start a transfer for a read request.
+    ///
+    /// Returns the ERROR packet to send to the client if the request cannot be served.
+    pub fn open(&self, request: &TFTPPacket) -> Result<TFTPTransfer<File>, TFTPPacket> {
+        let (filename, mode, options) = match request {
+            TFTPPacket::ReadRequest(filename, mode, options) => (filename, mode, options),
+            TFTPPacket::WriteRequest(..) => {
+                return Err(TFTPPacket::Error(
+                    TFTPErrorCode::AccessViolation,
+                    "Server is read-only".to_owned(),
+                ))
+            }
+            _ => {
+                return Err(TFTPPacket::Error(
+                    TFTPErrorCode::IllegalOperation,
+                    "Expected a request".to_owned(),
+                ))
+            }
+        };
+
+        // Files are always sent without netascii translation.
+        if !mode.eq_ignore_ascii_case("octet") && !mode.eq_ignore_ascii_case("netascii") {
+            return Err(TFTPPacket::Error(
+                TFTPErrorCode::IllegalOperation,
+                "Unsupported mode".to_owned(),
+            ));
+        }
+
+        let error = |code: TFTPErrorCode| TFTPPacket::Error(code, filename.to_owned());
+        let path = self.resolve(filename).map_err(error)?;
+        let file = File::open(path).map_err(|_| error(TFTPErrorCode::AccessViolation))?;
+        let size = file
+            .metadata()
+            .map_err(|_| error(TFTPErrorCode::AccessViolation))?
+            .len();
+
+        Ok(TFTPTransfer::new(file, size, options))
+    }
+
+    /// Answer requests received on `socket` until receiving from it fails.
+    ///
+    /// Each transfer is served from a new socket on its own thread, as
+    /// required by the transfer identifiers of RFC1350.
+    pub fn run(&self, socket: &UdpSocket) -> std::io::Result<()> {
+        let local_addr = socket.local_addr()?;
+        let mut buffer: [u8; 1500] = [0; 1500];
+
+        loop {
+            let (length, source) = socket.recv_from(&mut buffer)?;
+            let request = match TFTPPacket::from_bytes(&buffer[..length]) {
+                Some(p) => p,
+                None => continue,
+            };
+
+            match self.open(&request) {
+                Ok(transfer) => {
+                    let server = self.clone();
+                    std::thread::spawn(move || server.serve(transfer, local_addr, source));
+                }
+                Err(error) => {
+                    // Failing to tell one client about an error must not stop the server.
+                    let _ = socket.send_to(&error.to_bytes(), source);
+                }
+            }
+        }
+    }
+
+    fn serve(
+        &self,
+        mut transfer: TFTPTransfer<File>,
+        local_addr: SocketAddr,
+        client: SocketAddr,
+    ) -> std::io::Result<()> {
+        let socket = UdpSocket::bind(SocketAddr::new(local_addr.ip(), 0))?;
+        socket.connect(client)?;
+        socket.set_read_timeout(Some(Duration::from_secs(transfer.options().timeout as u64)))?;
+
+        let mut buffer: Vec<u8> = vec![0; 1500];
+        let mut packets = transfer.transmit()?;
+        let mut retries = 0;
+
+        loop {
+            for packet in &packets {
+                socket.send(&packet.to_bytes())?;
+            }
+
+            let length = match socket.recv(&mut buffer) {
+                Ok(length) => length,
+                Err(e)
+                    if e.kind() == std::io::ErrorKind::WouldBlock
+                        || e.kind() == std::io::ErrorKind::TimedOut =>
+                {
+                    retries += 1;
+                    if retries > self.retries {
+                        return Err(e);
+                    }
+                    packets = transfer.transmit()?;
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+
+            packets = match TFTPPacket::from_bytes(&buffer[..length]) {
+                Some(TFTPPacket::Acknowledgement(block)) => transfer.acknowledge(block)?,
+                Some(TFTPPacket::Error(..)) => return Ok(()),
+                _ => Vec::new(),
+            };
+
+            if transfer.is_finished() {
+                return Ok(());
+            }
+            if !packets.is_empty() {
+                retries = 0;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn options(options: &[(&str, &str)]) -> Vec<(String, String)> {
+        options
+            .iter()
+            .map(|(o, v)| (o.to_string(), v.to_string()))
+            .collect()
+    }
+
+    fn file(size: usize) -> Cursor<Vec<u8>> {
+        Cursor::new((0..size).map(|i| i as u8).collect())
+    }
+
+    #[test]
+    fn packet_round_trip() {
+        let packets = vec![
+            TFTPPacket::ReadRequest(
+                "pxelinux.0".to_owned(),
+                "octet".to_owned(),
+                options(&[("blksize", "1468"), ("tsize", "0")]),
+            ),
+            TFTPPacket::Data(7, vec![1, 2, 3]),
+            TFTPPacket::Data(8, Vec::new()),
+            TFTPPacket::Acknowledgement(65535),
+            TFTPPacket::Error(TFTPErrorCode::FileNotFound, "not found".to_owned()),
+            TFTPPacket::OptionAcknowledgement(options(&[("windowsize", "4")])),
+        ];
+
+        for packet in packets {
+            assert_eq!(TFTPPacket::from_bytes(&packet.to_bytes()), Some(packet));
+        }
+
+        assert_eq!(
+            TFTPPacket::ReadRequest("a".to_owned(), "octet".to_owned(), Vec::new()).to_bytes(),
+            b"\x00\x01a\x00octet\x00"
+        );
+        assert_eq!(TFTPPacket::from_bytes(b"\x00\x01a\x00octet"), None);
+    }
+
+    #[test]
+    fn negotiation() {
+        let requested = options(&[
+            ("BLKSIZE", "100000"),
+            ("tsize", "0"),
+            ("timeout", "0"),
+            ("windowsize", "8"),
+            ("unknown", "1"),
+        ]);
+        let (negotiated, acknowledged) = TFTPOptions::negotiate(&requested, 1234);
+
+        assert_eq!(
+            negotiated,
+            TFTPOptions {
+                block_size: 65464,
+                timeout: 5,
+                window_size: 8,
+            }
+        );
+        assert_eq!(
+            acknowledged,
+            options(&[("blksize", "65464"), ("tsize", "1234"), ("windowsize", "8")])
+        );
+    }
+
+    #[test]
+    fn option_acknowledgement() {
+        let requested = options(&[("blksize", "1024"), ("tsize", "0")]);
+        let mut transfer = TFTPTransfer::new(file(1500), 1500, &requested);
+
+        assert_eq!(
+            transfer.transmit().unwrap(),
+            vec![TFTPPacket::OptionAcknowledgement(options(&[
+                ("blksize", "1024"),
+                ("tsize", "1500")
+            ]))]
+        );
+
+        // Data only starts once the OACK is acknowledged with block 0.
+        assert!(transfer.acknowledge(1).unwrap().is_empty());
+        match &transfer.acknowledge(0).unwrap()[..] {
+            [TFTPPacket::Data(1, data)] => assert_eq!(data.len(), 1024),
+            packets => panic!("unexpected packets {:?}", packets),
+        }
+        match &transfer.acknowledge(1).unwrap()[..] {
+            [TFTPPacket::Data(2, data)] => assert_eq!(data.len(), 476),
+            packets => panic!("unexpected packets {:?}", packets),
+        }
+        assert!(transfer.acknowledge(2).unwrap().is_empty());
+        assert!(transfer.is_finished());
+    }
+
+    #[test]
+    fn final_empty_block() {
+        let mut transfer = TFTPTransfer::new(file(1024), 1024, &[]);
+
+        assert!(
+            matches!(&transfer.transmit().unwrap()[..], [TFTPPacket::Data(1, d)] if d.len() == 512)
+        );
+        assert!(
+            matches!(&transfer.acknowledge(1).unwrap()[..], [TFTPPacket::Data(2, d)] if d.len() == 512)
+        );
+        // A file filling the last block is terminated by an empty block.
+        assert_eq!(
+            transfer.acknowledge(2).unwrap(),
+            vec![TFTPPacket::Data(3, Vec::new())]
+        );
+        assert!(!transfer.is_finished());
+
+        // Duplicate acknowledgements are ignored with a window size of one.
+        assert!(transfer.acknowledge(2).unwrap().is_empty());
+        assert!(transfer.acknowledge(3).unwrap().is_empty());
+        assert!(transfer.is_finished());
+    }
+
+    #[test]
+    fn window() {
+        let requested = options(&[("blksize", "8"), ("windowsize", "4")]);
+        let mut transfer = TFTPTransfer::new(file(60), 60, &requested);
+        let blocks = |packets: Vec<TFTPPacket>| -> Vec<u16> {
+            packets
+                .into_iter()
+                .map(|p| match p {
+                    TFTPPacket::Data(block, _) => block,
+                    p => panic!("unexpected packet {:?}", p),
+                })
+                .collect()
+        };
+
+        transfer.transmit().unwrap();
+        assert_eq!(blocks(transfer.acknowledge(0).unwrap()), vec![1, 2, 3, 4]);
+
+        // A partial acknowledgement moves the window on from the acknowledged block.
+        assert_eq!(blocks(transfer.acknowledge(2).unwrap()), vec![3, 4, 5, 6]);
+        // Repeating the last acknowledgement resends the window.
+        assert_eq!(blocks(transfer.acknowledge(2).unwrap()), vec![3, 4, 5, 6]);
+        // Acknowledgements outside of the window are ignored.
+        assert!(transfer.acknowledge(7).unwrap().is_empty());
+
+        assert_eq!(blocks(transfer.acknowledge(6).unwrap()), vec![7, 8]);
+        match &transfer.transmit().unwrap()[..] {
+            [TFTPPacket::Data(7, a), TFTPPacket::Data(8, b)] => {
+                assert_eq!(a[..], (48..56).collect::<Vec<u8>>()[..]);
+                assert_eq!(b[..], [56, 57, 58, 59]);
+            }
+            packets => panic!("unexpected packets {:?}", packets),
+        }
+        assert!(transfer.acknowledge(8).unwrap().is_empty());
+        assert!(transfer.is_finished());
+    }
+
+    #[test]
+    fn block_number_wraps() {
+        let size = 8 * 65536 + 3;
+        let requested = options(&[("blksize", "8")]);
+        let mut transfer = TFTPTransfer::new(file(size), size as u64, &requested);
+        transfer.transmit().unwrap();
+
+        let mut packets = transfer.acknowledge(0).unwrap();
+        let mut sent: u64 = 0;
+        while let [TFTPPacket::Data(block, data)] = &packets[..] {
+            sent += 1;
+            assert_eq!(*block, sent as u16);
+            let expected = if sent == 65537 { 3 } else { 8 };
+            assert_eq!(data.len(), expected);
+            packets = transfer.acknowledge(*block).unwrap();
+        }
+
+        assert_eq!(sent, 65537);
+        assert!(transfer.is_finished());
+    }
+
+    // A root directory containing `file`, `sub/a` and `sub/dir`, with
+    // `secret` next to it outside of the root.
+    fn root(name: &str) -> PathBuf {
+        let base = std::env::temp_dir().join(format!("dhcprs-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&base);
+        std::fs::create_dir_all(base.join("root/sub/dir")).unwrap();
+        std::fs::write(base.join("root/file"), b"file").unwrap();
+        std::fs::write(base.join("root/sub/a"), b"a").unwrap();
+        std::fs::write(base.join("secret"), b"secret").unwrap();
+        base
+    }
+
+    #[test]
+    fn resolve() {
+        let base = root("resolve");
+        let server = TFTPServer::new(base.join("root")).unwrap();
+        let root = base.join("root").canonicalize().unwrap();
+
+        assert_eq!(server.resolve("file"), Ok(root.join("file")));
+        assert_eq!(server.resolve("/file"), Ok(root.join("file")));
+        assert_eq!(server.resolve("//sub/a"), Ok(root.join("sub/a")));
+        assert_eq!(server.resolve("sub\\a"), Ok(root.join("sub/a")));
+        assert_eq!(server.resolve("\\sub\\a"), Ok(root.join("sub/a")));
+        assert_eq!(server.resolve("./sub/./a"), Ok(root.join("sub/a")));
+
+        assert_eq!(
+            server.resolve("../secret"),
+            Err(TFTPErrorCode::AccessViolation)
+        );
+        assert_eq!(
+            server.resolve("..\\secret"),
+            Err(TFTPErrorCode::AccessViolation)
+        );
+        // Paths are not normalised, `..` is refused even below the root.
+        assert_eq!(
+            server.resolve("sub/../sub/a"),
+            Err(TFTPErrorCode::AccessViolation)
+        );
+
+        assert_eq!(server.resolve("sub/dir"), Err(TFTPErrorCode::FileNotFound));
+        assert_eq!(server.resolve("sub"), Err(TFTPErrorCode::FileNotFound));
+        assert_eq!(server.resolve("missing"), Err(TFTPErrorCode::FileNotFound));
+
+        std::fs::remove_dir_all(base).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn resolve_symbolic_links() {
+        let base = root("symlinks");
+        std::os::unix::fs::symlink(base.join("secret"), base.join("root/outside")).unwrap();
+        std::os::unix::fs::symlink(base.join("root/sub/a"), base.join("root/inside")).unwrap();
+        let server = TFTPServer::new(base.join("root")).unwrap();
+        let root = base.join("root").canonicalize().unwrap();
+
+        assert_eq!(
+            server.resolve("outside"),
+            Err(TFTPErrorCode::AccessViolation)
+        );
+        assert_eq!(server.resolve("inside"), Ok(root.join("sub/a")));
+
+        std::fs::remove_dir_all(base).unwrap();
+    }
+}
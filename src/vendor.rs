@@ -0,0 +1,474 @@
+//! Decoding and encoding of DHCP Option 43 "Vendor Specific Information".
+//!
+//! The contents of option 43 are only meaningful in the context of the
+//! vendor class (option 60) sent by the client. A `VendorRegistry` associates
+//! vendor class patterns with decoders so that the raw option data can be
+//! turned in to a typed `VendorInformation`.
+
+use crate::dhcp::DHCPOption;
+use crate::pxe::PXEOption;
+use std::net::Ipv4Addr;
+
+/// Enum representing the sub-options of option 43 for Microsoft clients ("MSFT 5.0").
+#[derive(Debug, Clone, PartialEq)]
+pub enum MicrosoftOption {
+    DisableNetBIOS(u32),          // 1 4 n1 n2 n3 n4
+    ReleaseLeaseOnShutdown(u32),  // 2 4 n1 n2 n3 n4
+    DefaultRouterMetricBase(u32), // 3 4 n1 n2 n3 n4
+
+    // Catchall
+    Option(u8, Vec<u8>),
+}
+
+/// Decoded contents of option 43.
+#[derive(Debug, Clone, PartialEq)]
+pub enum VendorInformation {
+    /// PXE clients, vendor class "PXEClient".
+    PXE(Vec<PXEOption>),
+    /// Microsoft Windows clients, vendor class "MSFT 5.0".
+    Microsoft(Vec<MicrosoftOption>),
+    /// Cisco lightweight access points, sub-option 241 listing the wireless LAN controllers.
+    CiscoAP(Vec<Ipv4Addr>),
+    /// Aruba access points, the controller address as a plain string.
+    ArubaAP(String),
+    /// RFC2132 encapsulated sub-options for a vendor without a specific decoder.
+    Encapsulated(Vec<(u8, Vec<u8>)>),
+    /// Data which could not be decoded.
+    Raw(Vec<u8>),
+}
+
+/// Function decoding the contents of option 43, returning `None` if the data is invalid.
+pub type VendorDecoder = fn(&[u8]) -> Option<VendorInformation>;
+
+fn read_u32(bytes: &[u8]) -> Option<u32> {
+    let bytes: [u8; 4] = bytes.try_into().ok()?;
+    Some(u32::from_be_bytes(bytes))
+}
+
+/// Splits RFC2132 style encapsulated options in to (code, data) pairs.
+///
+/// Pad options are skipped and decoding stops at an End option.
+pub fn decode_encapsulated(bytes: &[u8]) -> Option<Vec<(u8, Vec<u8>)>> {
+    let mut suboptions: Vec<(u8, Vec<u8>)> = Vec::new();
+
+    let mut position = 0;
+    while let Some(&code) = bytes.get(position) {
+        match code {
+            0 => position += 1,
+            255 => break,
+            code => {
+                let length = *bytes.get(position + 1)? as usize;
+                let data = bytes.get(position + 2..position + 2 + length)?;
+                suboptions.push((code, data.to_vec()));
+                position += 2 + length;
+            }
+        }
+    }
+
+    Some(suboptions)
+}
+
+/// Encodes (code, data) pairs as RFC2132 style encapsulated options.
+///
+/// Sub-options with more than 255 bytes of data cannot be encoded and are skipped.
+pub fn encode_encapsulated(suboptions: &[(u8, Vec<u8>)]) -> Vec<u8> {
+    let mut bytes: Vec<u8> = Vec::new();
+    for (code, data) in suboptions {
+        if data.len() > 255 {
+            continue;
+        }
+
+        bytes.push(*code);
+        bytes.push(data.len() as u8);
+        bytes.extend_from_slice(data);
+    }
+    bytes
+}
+
+/// Decoder for vendor class "MSFT 5.0".
+pub fn decode_microsoft(bytes: &[u8]) -> Option<VendorInformation> {
+    let options = decode_encapsulated(bytes)?
+        .into_iter()
+        .map(|(code, data)| match (code, read_u32(&data)) {
+            (1, Some(n)) => MicrosoftOption::DisableNetBIOS(n),
+            (2, Some(n)) => MicrosoftOption::ReleaseLeaseOnShutdown(n),
+            (3, Some(n)) => MicrosoftOption::DefaultRouterMetricBase(n),
+            _ => MicrosoftOption::Option(code, data),
+        })
+        .collect();
+
+    Some(VendorInformation::Microsoft(options))
+}
+
+/// Decoder for vendor classes starting with "Cisco AP".
+pub fn decode_cisco_ap(bytes: &[u8]) -> Option<VendorInformation> {
+    let suboptions = decode_encapsulated(bytes)?;
+    let (_, data) = suboptions.iter().find(|(code, _)| *code == 241)?;
+    if data.len() % 4 != 0 {
+        return None;
+    }
+
+    let controllers = data
+        .chunks(4)
+        .map(|c| Ipv4Addr::new(c[0], c[1], c[2], c[3]))
+        .collect();
+
+    Some(VendorInformation::CiscoAP(controllers))
+}
+
+/// Decoder for vendor class "ArubaAP".
+pub fn decode_aruba_ap(bytes: &[u8]) -> Option<VendorInformation> {
+    let controller = std::str::from_utf8(bytes).ok()?;
+    Some(VendorInformation::ArubaAP(controller.to_owned()))
+}
+
+/// Decoder used when no other decoder matches, treats the data as encapsulated options.
+pub fn decode_generic(bytes: &[u8]) -> Option<VendorInformation> {
+    Some(VendorInformation::Encapsulated(decode_encapsulated(bytes)?))
+}
+
+impl VendorInformation {
+    /// Encodes the vendor information to the contents of option 43.
+    ///
+    /// Sub-options too long to encode are left out.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        match self {
+            VendorInformation::PXE(options) => PXEOption::to_bytes(options),
+
+            VendorInformation::Microsoft(options) => {
+                let mut bytes: Vec<u8> = Vec::new();
+                for option in options {
+                    match option {
+                        MicrosoftOption::DisableNetBIOS(n) => {
+                            bytes.push(1);
+                            bytes.push(4);
+                            bytes.extend_from_slice(&n.to_be_bytes());
+                        }
+                        MicrosoftOption::ReleaseLeaseOnShutdown(n) => {
+                            bytes.push(2);
+                            bytes.push(4);
+                            bytes.extend_from_slice(&n.to_be_bytes());
+                        }
+                        MicrosoftOption::DefaultRouterMetricBase(n) => {
+                            bytes.push(3);
+                            bytes.push(4);
+                            bytes.extend_from_slice(&n.to_be_bytes());
+                        }
+                        MicrosoftOption::Option(n, b) => {
+                            if b.len() > 255 {
+                                continue;
+                            }
+
+                            bytes.push(*n);
+                            bytes.push(b.len() as u8);
+                            bytes.extend_from_slice(b);
+                        }
+                    }
+                }
+                bytes
+            }
+
+            VendorInformation::CiscoAP(controllers) => {
+                let mut bytes: Vec<u8> = Vec::new();
+                if controllers.len() > 63 {
+                    return bytes;
+                }
+
+                bytes.push(241);
+                bytes.push((controllers.len() * 4) as u8);
+                for controller in controllers {
+                    bytes.extend_from_slice(&controller.octets());
+                }
+                bytes
+            }
+
+            VendorInformation::ArubaAP(s) => s.as_bytes().to_vec(),
+            VendorInformation::Encapsulated(suboptions) => encode_encapsulated(suboptions),
+            VendorInformation::Raw(b) => b.clone(),
+        }
+    }
+}
+
+impl From<VendorInformation> for DHCPOption {
+    fn from(item: VendorInformation) -> Self {
+        DHCPOption::VendorSpecificInformation(item.to_bytes())
+    }
+}
+
+/// Registry associating vendor class patterns with option 43 decoders.
+///
+/// A pattern matches a vendor class identifier exactly, unless it ends in
+/// `*` in which case it matches any identifier starting with the rest of the
+/// pattern. Patterns registered later take precedence over earlier ones.
+pub struct VendorRegistry {
+    decoders: Vec<(Vec<u8>, VendorDecoder)>,
+}
+
+impl Default for VendorRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl VendorRegistry {
+    /// Creates a registry with the decoders provided by dhcprs registered.
+    pub fn new() -> Self {
+        let mut registry = Self::empty();
+        registry.register(b"PXEClient*", crate::pxe::decode_pxe);
+        registry.register(b"MSFT 5.0", decode_microsoft);
+        registry.register(b"Cisco AP*", decode_cisco_ap);
+        registry.register(b"ArubaAP", decode_aruba_ap);
+        registry
+    }
+
+    /// Creates a registry without any decoders registered.
+    pub fn empty() -> Self {
+        Self {
+            decoders: Vec::new(),
+        }
+    }
+
+    /// Registers a decoder for vendor classes matching `pattern`.
+    pub fn register(&mut self, pattern: &[u8], decoder: VendorDecoder) {
+        self.decoders.push((pattern.to_vec(), decoder));
+    }
+
+    /// Finds the decoder for the given vendor class identifier.
+    pub fn find(&self, class_identifier: &[u8]) -> Option<VendorDecoder> {
+        self.decoders
+            .iter()
+            .rev()
+            .find(|(pattern, _)| match pattern.strip_suffix(b"*") {
+                Some(prefix) => class_identifier.starts_with(prefix),
+                None => class_identifier == pattern.as_slice(),
+            })
+            .map(|(_, decoder)| *decoder)
+    }
+
+    /// Decodes the contents of option 43 sent with the given vendor class identifier.
+    ///
+    /// Falls back to generic encapsulated options and then to the raw data if
+    /// the data cannot be decoded.
+    pub fn decode(&self, class_identifier: &[u8], bytes: &[u8]) -> VendorInformation {
+        self.find(class_identifier)
+            .and_then(|decoder| decoder(bytes))
+            .or_else(|| decode_generic(bytes))
+            .unwrap_or_else(|| VendorInformation::Raw(bytes.to_vec()))
+    }
+
+    /// Decodes option 43 from a list of options using the vendor class in option 60.
+    ///
+    /// Returns `None` if there is no option 43 in the list.
+    pub fn decode_options(&self, options: &[DHCPOption]) -> Option<VendorInformation> {
+        let mut class_identifier: &[u8] = &[];
+        let mut vendor_information: Option<&[u8]> = None;
+
+        for option in options {
+            match option {
+                DHCPOption::ClassIdentifier(b) => class_identifier = b,
+                DHCPOption::VendorSpecificInformation(b) => vendor_information = Some(b),
+                _ => (),
+            }
+        }
+
+        Some(self.decode(class_identifier, vendor_information?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pxe::PXEDiscoveryControl;
+
+    const CONTROLLER: Ipv4Addr = Ipv4Addr::new(192, 0, 2, 1);
+
+    fn decode_raw(bytes: &[u8]) -> Option<VendorInformation> {
+        Some(VendorInformation::Raw(bytes.to_vec()))
+    }
+
+    #[test]
+    fn registry_patterns() {
+        let registry = VendorRegistry::new();
+        let pxe = [6, 1, 8, 255];
+        let discovery_control =
+            VendorInformation::PXE(vec![PXEOption::DiscoveryControl(PXEDiscoveryControl {
+                use_bootfile: true,
+                ..Default::default()
+            })]);
+
+        // Patterns ending in `*` match by prefix, others exactly.
+        assert_eq!(
+            registry.decode(b"PXEClient:Arch:00007:UNDI:003016", &pxe),
+            discovery_control
+        );
+        assert_eq!(registry.decode(b"PXEClient", &pxe), discovery_control);
+        assert_eq!(
+            registry.decode(b"Cisco AP c3600", &[241, 4, 192, 0, 2, 1]),
+            VendorInformation::CiscoAP(vec![CONTROLLER])
+        );
+        assert_eq!(
+            registry.decode(b"MSFT 5.0", &[1, 4, 0, 0, 0, 1]),
+            VendorInformation::Microsoft(vec![MicrosoftOption::DisableNetBIOS(1)])
+        );
+        assert_eq!(
+            registry.decode(b"MSFT 5.0 XBOX", &[1, 4, 0, 0, 0, 1]),
+            VendorInformation::Encapsulated(vec![(1, vec![0, 0, 0, 1])])
+        );
+        assert_eq!(
+            registry.decode(b"ArubaAP", b"192.0.2.1"),
+            VendorInformation::ArubaAP("192.0.2.1".to_owned())
+        );
+        assert_eq!(
+            registry.decode(b"ArubaAP 1.0", b"192.0.2.1"),
+            VendorInformation::Raw(b"192.0.2.1".to_vec())
+        );
+    }
+
+    #[test]
+    fn registry_precedence() {
+        let mut registry = VendorRegistry::new();
+        registry.register(b"PXEClient:Arch:00007*", decode_raw);
+        let pxe = [71, 4, 0, 1, 0, 0, 255];
+
+        assert_eq!(
+            registry.decode(b"PXEClient:Arch:00007:UNDI:003016", &pxe),
+            VendorInformation::Raw(pxe.to_vec())
+        );
+        assert_eq!(
+            registry.decode(b"PXEClient:Arch:00000:UNDI:002001", &pxe),
+            VendorInformation::PXE(vec![PXEOption::BootItem(1, 0)])
+        );
+
+        // A later, more general pattern overrides an earlier specific one.
+        registry.register(b"*", decode_raw);
+        assert_eq!(
+            registry.decode(b"MSFT 5.0", &[1, 4, 0, 0, 0, 1]),
+            VendorInformation::Raw(vec![1, 4, 0, 0, 0, 1])
+        );
+
+        let empty = VendorRegistry::empty();
+        assert!(empty.find(b"PXEClient").is_none());
+        assert_eq!(
+            empty.decode(b"PXEClient", &pxe),
+            VendorInformation::Encapsulated(vec![(71, vec![0, 1, 0, 0])])
+        );
+    }
+
+    #[test]
+    fn registry_options() {
+        let registry = VendorRegistry::new();
+        let options = [
+            DHCPOption::VendorSpecificInformation(vec![241, 4, 192, 0, 2, 1]),
+            DHCPOption::ClassIdentifier(b"Cisco AP c1140".to_vec()),
+        ];
+        assert_eq!(
+            registry.decode_options(&options),
+            Some(VendorInformation::CiscoAP(vec![CONTROLLER]))
+        );
+        assert_eq!(registry.decode_options(&options[1..]), None);
+
+        // Without a vendor class only the generic decoder is used.
+        assert_eq!(
+            registry.decode_options(&options[..1]),
+            Some(VendorInformation::Encapsulated(vec![(
+                241,
+                vec![192, 0, 2, 1]
+            )]))
+        );
+    }
+
+    #[test]
+    fn encapsulated() {
+        let bytes = [1, 2, 3, 4, 0, 0, 2, 0, 255, 9, 9];
+        let suboptions = vec![(1, vec![3, 4]), (2, Vec::new())];
+        assert_eq!(decode_encapsulated(&bytes), Some(suboptions.clone()));
+        assert_eq!(encode_encapsulated(&suboptions), [1, 2, 3, 4, 2, 0]);
+        assert_eq!(
+            decode_generic(&bytes),
+            Some(VendorInformation::Encapsulated(suboptions))
+        );
+
+        assert_eq!(decode_encapsulated(&[1, 3, 0, 0]), None);
+        assert_eq!(decode_encapsulated(&[1]), None);
+        assert_eq!(
+            VendorRegistry::new().decode(b"", &[1, 3, 0, 0]),
+            VendorInformation::Raw(vec![1, 3, 0, 0])
+        );
+
+        // Sub-options too long to encode are skipped.
+        assert_eq!(
+            encode_encapsulated(&[(1, vec![0; 256]), (2, vec![1])]),
+            [2, 1, 1]
+        );
+        assert_eq!(encode_encapsulated(&[(1, vec![0; 255])]).len(), 257);
+    }
+
+    #[test]
+    fn microsoft() {
+        let bytes = [
+            1, 4, 0, 0, 0, 2, 2, 4, 0, 0, 0, 1, 3, 4, 0, 0, 0, 10, 3, 1, 7,
+        ];
+        let information = VendorInformation::Microsoft(vec![
+            MicrosoftOption::DisableNetBIOS(2),
+            MicrosoftOption::ReleaseLeaseOnShutdown(1),
+            MicrosoftOption::DefaultRouterMetricBase(10),
+            MicrosoftOption::Option(3, vec![7]),
+        ]);
+        assert_eq!(decode_microsoft(&bytes), Some(information.clone()));
+        assert_eq!(information.to_bytes(), bytes);
+        assert_eq!(decode_microsoft(&[1, 4, 0]), None);
+
+        let overlong = VendorInformation::Microsoft(vec![
+            MicrosoftOption::Option(9, vec![0; 256]),
+            MicrosoftOption::DisableNetBIOS(1),
+        ]);
+        assert_eq!(overlong.to_bytes(), [1, 4, 0, 0, 0, 1]);
+    }
+
+    #[test]
+    fn cisco_ap() {
+        let bytes = [1, 1, 0, 241, 8, 192, 0, 2, 1, 192, 0, 2, 2];
+        let second = Ipv4Addr::new(192, 0, 2, 2);
+        assert_eq!(
+            decode_cisco_ap(&bytes),
+            Some(VendorInformation::CiscoAP(vec![CONTROLLER, second]))
+        );
+        assert_eq!(
+            VendorInformation::CiscoAP(vec![CONTROLLER, second]).to_bytes(),
+            bytes[3..]
+        );
+
+        assert_eq!(decode_cisco_ap(&[241, 3, 192, 0, 2]), None);
+        assert_eq!(decode_cisco_ap(&[1, 1, 0]), None);
+
+        // At most 63 controllers fit in the sub-option.
+        let controllers = vec![CONTROLLER; 63];
+        let bytes = VendorInformation::CiscoAP(controllers.clone()).to_bytes();
+        assert_eq!(bytes[..2], [241, 252]);
+        assert_eq!(
+            decode_cisco_ap(&bytes),
+            Some(VendorInformation::CiscoAP(controllers))
+        );
+        assert!(VendorInformation::CiscoAP(vec![CONTROLLER; 64])
+            .to_bytes()
+            .is_empty());
+    }
+
+    #[test]
+    fn aruba_ap() {
+        let information = VendorInformation::ArubaAP("192.0.2.1".to_owned());
+        assert_eq!(decode_aruba_ap(b"192.0.2.1"), Some(information.clone()));
+        assert_eq!(information.to_bytes(), b"192.0.2.1");
+        assert_eq!(decode_aruba_ap(&[0xFF]), None);
+    }
+
+    #[test]
+    fn vendor_specific_information() {
+        let information = VendorInformation::Encapsulated(vec![(1, vec![2])]);
+        assert_eq!(
+            DHCPOption::from(information),
+            DHCPOption::VendorSpecificInformation(vec![1, 1, 2])
+        );
+        assert_eq!(VendorInformation::Raw(vec![1, 2, 3]).to_bytes(), [1, 2, 3]);
+    }
+}